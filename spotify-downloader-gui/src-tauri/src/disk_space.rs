@@ -0,0 +1,148 @@
+// Free disk space checks, so a large download doesn't fail halfway on a full drive.
+use std::fs;
+use std::path::{Component, Path};
+use std::process::{Command, Stdio};
+
+const MB: u64 = 1024 * 1024;
+
+// Length assumed for an average song when estimating its file size
+const AVERAGE_TRACK_SECS: u64 = 240;
+
+/// Rough size of one song in bytes: from the bitrate when one is set, else a typical size for the format
+pub fn estimated_track_bytes(format: &str, bitrate: Option<&str>) -> u64 {
+    estimated_bytes(format, bitrate, AVERAGE_TRACK_SECS)
+}
+
+/// Rough size of `secs` of audio in bytes. Only an estimate: variable bitrates and
+/// lossless compression make the real files bigger or smaller
+pub fn estimated_bytes(format: &str, bitrate: Option<&str>, secs: u64) -> u64 {
+    let kbps = bitrate
+        .and_then(|b| b.strip_suffix('k'))
+        .and_then(|b| b.parse::<u64>().ok())
+        .filter(|_| !matches!(format, "flac" | "wav"));
+    match kbps {
+        Some(kbps) => kbps * 1000 / 8 * secs,
+        None => {
+            let typical_track = match format {
+                "flac" => 30 * MB,
+                "wav" => 42 * MB,
+                "opus" => 5 * MB,
+                _ => 8 * MB,
+            };
+            typical_track * secs / AVERAGE_TRACK_SECS
+        }
+    }
+}
+
+/// A size for people to read, e.g. "84.2 MB" or "1.3 GB"
+pub fn format_size(bytes: u64) -> String {
+    const GB: u64 = 1024 * MB;
+    if bytes >= GB {
+        format!("{:.1} GB", bytes as f64 / GB as f64)
+    } else {
+        format!("{:.1} MB", bytes as f64 / MB as f64)
+    }
+}
+
+/// Bytes available to the user on the filesystem holding `path`. The path
+/// itself may not exist yet, the nearest existing parent is measured instead
+pub fn free_space(path: &Path) -> Result<u64, String> {
+    let existing = path
+        .ancestors()
+        .find(|dir| dir.exists())
+        .ok_or_else(|| format!("No existing folder found for {}", path.display()))?;
+    query_free_space(existing)
+}
+
+#[cfg(unix)]
+fn query_free_space(path: &Path) -> Result<u64, String> {
+    // POSIX output: a header, then "Filesystem 1024-blocks Used Available Capacity Mounted"
+    let output = Command::new("df")
+        .arg("-Pk")
+        .arg(path)
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| format!("Failed to run df: {}", e))?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    stdout
+        .lines()
+        .nth(1)
+        .and_then(|line| line.split_whitespace().nth(3))
+        .and_then(|available| available.parse::<u64>().ok())
+        .map(|kb| kb * 1024)
+        .ok_or_else(|| format!("Could not read free space for {}", path.display()))
+}
+
+#[cfg(windows)]
+fn query_free_space(path: &Path) -> Result<u64, String> {
+    let path = path.to_string_lossy().replace('\'', "''");
+    let output = Command::new("powershell")
+        .args(["-NoProfile", "-Command"])
+        .arg(format!("([System.IO.DriveInfo]::new('{}')).AvailableFreeSpace", path))
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| format!("Failed to run powershell: {}", e))?;
+    String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .parse::<u64>()
+        .map_err(|_| format!("Could not read free space for {}", path))
+}
+
+// Folders removable drives are mounted under. Once a drive is gone its mount point usually is too
+#[cfg(unix)]
+const MOUNT_BASES: &[&str] = &["/media", "/run/media", "/mnt", "/Volumes"];
+
+/// Refuse a folder on a drive that isn't there, e.g. an unplugged USB stick, before
+/// creating it would put the songs in a plain folder on the system drive instead
+pub fn check_drive(path: &Path) -> Result<(), String> {
+    if path.is_relative() {
+        return Ok(());
+    }
+    let unavailable = || Err(format!("Target drive not available: {} is not mounted, plug the drive back in or pick another folder", path.display()));
+
+    // The drive letter's root, like E:\
+    let root: std::path::PathBuf = path.components().take_while(|c| matches!(c, Component::Prefix(_) | Component::RootDir)).collect();
+    if root.as_os_str().is_empty() || !root.exists() {
+        return unavailable();
+    }
+
+    #[cfg(unix)]
+    {
+        // The nearest folder that exists being a mount base, or a user's folder in one, means
+        // the drive's own mount point is missing
+        let existing = path.ancestors().find(|dir| dir.exists()).unwrap_or(&root);
+        let is_base = |dir: &Path| MOUNT_BASES.iter().any(|base| dir == Path::new(base));
+        if existing != path && (is_base(existing) || existing.parent().is_some_and(|parent| parent == Path::new("/media") || parent == Path::new("/run/media"))) {
+            return unavailable();
+        }
+    }
+    Ok(())
+}
+
+/// Write and delete a small file in `dir`, so a read-only or vanished drive is reported
+/// before spotdl starts rather than as a cryptic failure partway through
+pub fn check_writable(dir: &Path) -> Result<(), String> {
+    let probe = dir.join(format!(".spotify-downloader-write-test-{}", std::process::id()));
+    fs::write(&probe, b"")
+        .map_err(|e| format!("Target drive not available: {} can't be written to ({})", dir.display(), e))?;
+    let _ = fs::remove_file(&probe);
+    Ok(())
+}
+
+/// Refuse a download whose estimated size, plus `reserve_mb` left over, doesn't fit.
+/// Without a known track count only the reserve is checked. When the free space
+/// can't be measured the download is allowed rather than blocked
+pub fn check(path: &Path, format: &str, bitrate: Option<&str>, expected_tracks: Option<u32>, reserve_mb: u64) -> Result<(), String> {
+    let Ok(free) = free_space(path) else { return Ok(()) };
+
+    let estimate = expected_tracks.map_or(0, |tracks| tracks as u64 * estimated_track_bytes(format, bitrate));
+    if free < estimate + reserve_mb * MB {
+        return Err(format!(
+            "Not enough disk space: {} MB free, about {} MB needed for the songs plus {} MB kept free",
+            free / MB,
+            estimate.div_ceil(MB),
+            reserve_mb
+        ));
+    }
+    Ok(())
+}
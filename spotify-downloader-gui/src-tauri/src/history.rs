@@ -0,0 +1,90 @@
+// Download history, stored as a JSON array in the app data directory.
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+const HISTORY_FILE: &str = "history.json";
+
+// Serializes read-modify-write cycles on the history file
+static HISTORY_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub url: String,
+    pub content_type: String,
+    pub collection: Option<String>,
+    pub output_path: String,
+    pub timestamp: String,  // RFC 3339, local time
+    // Missing from entries written by older versions
+    #[serde(default)]
+    pub track_count: u32,
+    #[serde(default)]
+    pub format: Option<String>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct CollectionCount {
+    pub name: String,
+    pub count: usize,
+}
+
+fn history_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = app.path().app_data_dir()
+        .map_err(|e| format!("Failed to resolve data directory: {}", e))?;
+    Ok(dir.join(HISTORY_FILE))
+}
+
+fn read_entries(path: &PathBuf) -> Vec<HistoryEntry> {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+/// All history entries, oldest first
+pub fn load(app: &AppHandle) -> Result<Vec<HistoryEntry>, String> {
+    let path = history_path(app)?;
+    let _guard = HISTORY_LOCK.lock();
+    Ok(read_entries(&path))
+}
+
+/// Add an entry to the end of the history file
+pub fn append(app: &AppHandle, entry: HistoryEntry) -> Result<(), String> {
+    let path = history_path(app)?;
+    let _guard = HISTORY_LOCK.lock();
+
+    let mut entries = read_entries(&path);
+    entries.push(entry);
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("Failed to create data directory: {}", e))?;
+    }
+    let contents = serde_json::to_string_pretty(&entries)
+        .map_err(|e| format!("Failed to serialize history: {}", e))?;
+    fs::write(&path, contents).map_err(|e| format!("Failed to write history: {}", e))
+}
+
+/// Delete every history entry
+pub fn clear(app: &AppHandle) -> Result<(), String> {
+    let path = history_path(app)?;
+    let _guard = HISTORY_LOCK.lock();
+    match fs::remove_file(&path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(format!("Failed to clear history: {}", e)),
+        _ => Ok(()),
+    }
+}
+
+/// Distinct collection names with how many downloads each holds, sorted by name
+pub fn collections(entries: &[HistoryEntry]) -> Vec<CollectionCount> {
+    let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+    for name in entries.iter().filter_map(|entry| entry.collection.as_deref()) {
+        *counts.entry(name).or_default() += 1;
+    }
+
+    counts.into_iter()
+        .map(|(name, count)| CollectionCount { name: name.to_string(), count })
+        .collect()
+}
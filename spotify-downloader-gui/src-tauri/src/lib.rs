@@ -1,18 +1,66 @@
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
-use std::process::{Command, Stdio};
-use std::path::Path;
+mod disk_space;
+mod history;
+mod library;
+mod log_file;
+mod manifest;
+mod metadata;
+mod parser;
+mod progress_server;
+mod queue;
+mod quiet_hours;
+mod resume;
+mod settings;
+mod spotify_url;
+mod thermal;
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::path::{Path, PathBuf};
 use std::fs;
 use std::io::{BufRead, BufReader};
-use std::time::Instant;
-use std::sync::{Arc, Mutex, atomic::{AtomicBool, AtomicU32, Ordering}};
+use std::time::{Duration, Instant, SystemTime};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError, atomic::{AtomicBool, AtomicU8, AtomicU32, Ordering}, mpsc};
 use std::thread;
-use tauri::{AppHandle, Emitter};
-use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager, ipc::Channel};
+use tauri_plugin_notification::NotificationExt;
+use tauri_plugin_opener::OpenerExt;
+use serde::{Deserialize, Serialize};
+use history::{CollectionCount, HistoryEntry};
+use library::{DownloadedTrack, IntegrityIssue};
+use log_file::LogFile;
+use manifest::Manifest;
+use metadata::TrackInfo;
+use parser::{ErrorKind, Severity, SpotdlEvent};
+use progress_server::ProgressServer;
+use queue::{DownloadQueue, QueuedDownload, UrlImport};
+use quiet_hours::QuietHours;
+use resume::{InterruptedDownload, ResumeInfo};
+use settings::{Settings, SlowDownloadConfig};
+use spotify_url::{ContentKind, normalize_spotify_url, validate_spotify_url};
+use thermal::ThermalGuard;
 
 // Global state for the download process
 struct DownloadState {
     child_pid: AtomicU32,
     is_cancelled: AtomicBool,
+    pause_reasons: AtomicU8,
+    last_progress: Mutex<Option<ProgressPayload>>,
+    busy: AtomicBool,  // a download or benchmark owns child_pid
+    last_failed: Mutex<Option<FailedDownload>>,
+    per_track: AtomicBool,  // the running download starts one spotdl per song
+    skip_requested: AtomicBool,
+    progress_channel: Mutex<Option<Channel<ProgressPayload>>>,  // the running download's caller, if it passed one
+    output_dir: Mutex<Option<PathBuf>>,  // where the running download saves, checked again on resume
+}
+
+// Songs that failed in the last finished download, kept for retry_failed
+#[derive(Clone)]
+struct FailedDownload {
+    url: String,
+    kind: ContentKind,
+    options: DownloadOptions,
+    tracks: Vec<String>,
 }
 
 impl Default for DownloadState {
@@ -20,328 +68,3771 @@ impl Default for DownloadState {
         Self {
             child_pid: AtomicU32::new(0),
             is_cancelled: AtomicBool::new(false),
+            pause_reasons: AtomicU8::new(0),
+            last_progress: Mutex::new(None),
+            busy: AtomicBool::new(false),
+            last_failed: Mutex::new(None),
+            per_track: AtomicBool::new(false),
+            skip_requested: AtomicBool::new(false),
+            progress_channel: Mutex::new(None),
+            output_dir: Mutex::new(None),
+        }
+    }
+}
+
+const BUSY_MESSAGE: &str = "A download is already in progress";
+const ARTIST_PREVIEW_MESSAGE: &str = "Artist downloads can be very large, preview the songs and confirm before downloading";
+
+// Holds DownloadState::busy until dropped, so every exit path releases it along
+// with the download's progress channel
+struct BusyGuard<'a>(&'a DownloadState);
+
+impl<'a> BusyGuard<'a> {
+    fn acquire(state: &'a DownloadState) -> Result<Self, String> {
+        state.busy.compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+            .map(|_| Self(state))
+            .map_err(|_| BUSY_MESSAGE.to_string())
+    }
+}
+
+impl Drop for BusyGuard<'_> {
+    fn drop(&mut self) {
+        self.0.busy.store(false, Ordering::SeqCst);
+        if let Ok(mut channel) = self.0.progress_channel.lock() {
+            *channel = None;
+        }
+    }
+}
+
+// Resets what a running download sets on DownloadState when dropped, so an early error
+// return doesn't leave it for the next command. A download that never reached its end
+// also forgets its saved copy, as there is nothing to continue
+struct DownloadCleanup<'a> {
+    app: &'a AppHandle,
+    state: &'a DownloadState,
+    finished: bool,
+}
+
+impl Drop for DownloadCleanup<'_> {
+    fn drop(&mut self) {
+        // Clear the child PID and the pauses held on it. Quiet hours stay, so the next download waits
+        self.state.child_pid.store(0, Ordering::SeqCst);
+        self.state.pause_reasons.fetch_and(PAUSE_QUIET_HOURS, Ordering::SeqCst);
+        self.state.per_track.store(false, Ordering::SeqCst);
+        self.state.skip_requested.store(false, Ordering::SeqCst);
+        *lock_recovering(&self.state.output_dir) = None;
+        if !self.finished {
+            let _ = resume::clear(self.app);
+        }
+    }
+}
+
+// Reasons a download can be paused for; it only resumes once all are cleared
+const PAUSE_QUIET_HOURS: u8 = 1 << 0;
+const PAUSE_THERMAL: u8 = 1 << 1;
+const PAUSE_USER: u8 = 1 << 2;
+
+/// Send a signal to the download's process group, and to the process itself as fallback
+#[cfg(unix)]
+fn send_signal(pid: u32, signal: &str) {
+    let _ = Command::new("kill")
+        .arg(format!("-{}", signal))
+        .arg(format!("-{}", pid))
+        .output();
+
+    let _ = Command::new("kill")
+        .arg(format!("-{}", signal))
+        .arg(pid.to_string())
+        .output();
+}
+
+/// Windows has no job control signals, so pausing is not supported there
+#[cfg(not(unix))]
+fn send_signal(_pid: u32, _signal: &str) {}
+
+/// Terminate the download together with the ffmpeg processes spotdl started
+#[cfg(unix)]
+fn terminate_process(pid: u32) {
+    send_signal(pid, "TERM");
+}
+
+/// Terminate the download together with the ffmpeg processes spotdl started
+#[cfg(windows)]
+fn terminate_process(pid: u32) {
+    use std::os::windows::process::CommandExt;
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+
+    // /T takes the whole process tree down, /F because console-less processes ignore a polite close
+    let _ = Command::new("taskkill")
+        .args(["/PID", &pid.to_string(), "/T", "/F"])
+        .creation_flags(CREATE_NO_WINDOW)
+        .output();
+}
+
+/// Kill the download and its process group outright, for when it ignores a terminate
+#[cfg(unix)]
+fn kill_process(pid: u32) {
+    send_signal(pid, "KILL");
+}
+
+/// taskkill /F is already forceful, so this is the same as terminating
+#[cfg(windows)]
+fn kill_process(pid: u32) {
+    terminate_process(pid);
+}
+
+// How long a cancelled download gets to exit on its own before it is killed
+const KILL_GRACE: Duration = Duration::from_secs(5);
+const EXIT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Wait for spotdl to exit. Once cancelled or skipped it gets KILL_GRACE to shut down
+/// before it is killed, so a process ignoring SIGTERM can't leave the command hanging
+fn wait_for_exit(child: &mut Child, state: &DownloadState) -> Result<ExitStatus, String> {
+    wait_for_exit_with(child, state, || {})
+}
+
+/// wait_for_exit, calling `tick` every EXIT_POLL_INTERVAL while spotdl runs
+fn wait_for_exit_with(child: &mut Child, state: &DownloadState, mut tick: impl FnMut()) -> Result<ExitStatus, String> {
+    let mut cancelled_at = None;
+    loop {
+        tick();
+        if let Some(status) = child.try_wait().map_err(|e| format!("Failed to wait for spotdl: {}", e))? {
+            return Ok(status);
+        }
+
+        if state.is_cancelled.load(Ordering::SeqCst) || state.skip_requested.load(Ordering::SeqCst) {
+            let cancelled_at = *cancelled_at.get_or_insert_with(Instant::now);
+            if cancelled_at.elapsed() >= KILL_GRACE {
+                kill_process(child.id());
+                let _ = child.kill();
+                return child.wait().map_err(|e| format!("Failed to wait for spotdl: {}", e));
+            }
+        }
+        thread::sleep(EXIT_POLL_INTERVAL);
+    }
+}
+
+/// Run the child in its own process group so signals reach its ffmpeg children too
+fn isolate_process_group(cmd: &mut Command) {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        cmd.process_group(0);
+    }
+    #[cfg(not(unix))]
+    let _ = cmd;
+}
+
+/// Pause the active download for `reason`, returns false when nothing is running
+fn pause_child(state: &DownloadState, reason: u8) -> bool {
+    let pid = state.child_pid.load(Ordering::SeqCst);
+    if pid == 0 {
+        return false;
+    }
+
+    let previous = state.pause_reasons.fetch_or(reason, Ordering::SeqCst);
+    if previous == 0 {
+        send_signal(pid, "STOP");
+    }
+    true
+}
+
+/// Clear a pause `reason`, resuming the download once no other reason holds it
+fn resume_child(state: &DownloadState, reason: u8) -> bool {
+    let previous = state.pause_reasons.fetch_and(!reason, Ordering::SeqCst);
+    if previous & reason == 0 {
+        return false;
+    }
+
+    let pid = state.child_pid.load(Ordering::SeqCst);
+    if previous == reason && pid > 0 {
+        send_signal(pid, "CONT");
+    }
+    true
+}
+
+#[derive(Clone, Serialize)]
+struct ProgressPayload {
+    permille: u32,  // 0-1000, fine enough for a smooth bar at any track count
+    percent: u32,  // permille / 10, for clients that only know whole percents
+    message: String,
+    current_track: u32,
+    total_tracks: u32,
+    speed: String,  // e.g., "2.5 songs/min"
+    eta_seconds: Option<u32>,  // None until at least one song is done and the total is known
+    bytes_per_sec: Option<u64>,  // latest transfer rate, when the output reports one
+    current_title: Option<String>,  // "Artist - Title" of the song the output last named
+}
+
+/// Send a progress update to the UI and any attached progress server clients. A download
+/// started with a progress channel only reports to its caller; the global
+/// "download-progress" event is kept for callers that don't pass one
+fn emit_progress(app: &AppHandle, payload: ProgressPayload) {
+    let state = app.state::<DownloadState>();
+    if let Ok(mut last) = state.last_progress.lock() {
+        *last = Some(payload.clone());
+    }
+    app.state::<ProgressServer>().broadcast(&payload);
+
+    let channel = state.progress_channel.lock().ok().and_then(|channel| channel.clone());
+    match channel {
+        Some(channel) => {
+            let _ = channel.send(payload);
+        }
+        None => {
+            let _ = app.emit("download-progress", payload);
+        }
+    }
+}
+
+/// The latest progress update of the running download, None when nothing is running.
+/// Returned rather than emitted, as a download's progress channel belongs to the window
+/// that started it and never reaches a reloaded or second one
+#[tauri::command]
+fn get_current_progress(state: tauri::State<DownloadState>) -> Option<ProgressPayload> {
+    if !state.busy.load(Ordering::SeqCst) {
+        return None;
+    }
+    lock_recovering(&state.last_progress).clone()
+}
+
+/// Start the local progress websocket on 127.0.0.1 (port 0 picks a free one)
+#[tauri::command]
+fn start_progress_server(port: u16, server: tauri::State<ProgressServer>) -> Result<u16, String> {
+    server.start(port)
+}
+
+/// Stop the local progress websocket and disconnect its clients
+#[tauri::command]
+fn stop_progress_server(server: tauri::State<ProgressServer>) -> Result<(), String> {
+    server.stop()
+}
+
+/// Get the configured quiet hours window, if any
+#[tauri::command]
+fn get_quiet_hours(settings: tauri::State<Mutex<Settings>>) -> Option<QuietHours> {
+    settings.lock().ok().and_then(|settings| settings.quiet_hours.clone())
+}
+
+/// Set (or clear with `null`) the daily window during which downloads stay paused
+#[tauri::command]
+fn set_quiet_hours(
+    app: AppHandle,
+    quiet_hours: Option<QuietHours>,
+    settings: tauri::State<Mutex<Settings>>,
+) -> Result<(), String> {
+    if let Some(window) = &quiet_hours {
+        window.validate()?;
+    }
+
+    let mut settings = settings.lock().map_err(|_| "Settings are unavailable".to_string())?;
+    settings.quiet_hours = quiet_hours;
+    settings::save(&app, &settings)
+}
+
+/// Get the thermal guard limits, `null` when the guard is off
+#[tauri::command]
+fn get_thermal_guard(settings: tauri::State<Mutex<Settings>>) -> Option<ThermalGuard> {
+    settings.lock().ok().and_then(|settings| settings.thermal_guard.clone())
+}
+
+/// Turn on (or off with `null`) pausing downloads while the CPU is too hot
+#[tauri::command]
+fn set_thermal_guard(
+    app: AppHandle,
+    thermal_guard: Option<ThermalGuard>,
+    settings: tauri::State<Mutex<Settings>>,
+) -> Result<(), String> {
+    if let Some(guard) = &thermal_guard {
+        guard.validate()?;
+    }
+
+    let mut settings = settings.lock().map_err(|_| "Settings are unavailable".to_string())?;
+    settings.thermal_guard = thermal_guard;
+    settings::save(&app, &settings)
+}
+
+/// Current CPU temperature in °C, `null` where no sensor is available
+#[tauri::command]
+fn get_cpu_temperature() -> Option<f32> {
+    thermal::cpu_temperature()
+}
+
+/// Get past downloads, optionally only those in one collection
+#[tauri::command]
+fn get_history(app: AppHandle, collection: Option<String>) -> Result<Vec<HistoryEntry>, String> {
+    let entries = history::load(&app)?;
+    Ok(match collection {
+        Some(name) => entries.into_iter().filter(|e| e.collection.as_deref() == Some(name.as_str())).collect(),
+        None => entries,
+    })
+}
+
+/// Forget every past download
+#[tauri::command]
+fn clear_history(app: AppHandle) -> Result<(), String> {
+    history::clear(&app)
+}
+
+/// Get the distinct collection names used in the history with their download counts
+#[tauri::command]
+fn get_collections(app: AppHandle) -> Result<Vec<CollectionCount>, String> {
+    Ok(history::collections(&history::load(&app)?))
+}
+
+/// Get the slow download warning threshold
+#[tauri::command]
+fn get_slow_download_config(settings: tauri::State<Mutex<Settings>>) -> Result<SlowDownloadConfig, String> {
+    settings.lock()
+        .map(|settings| settings.slow_download.clone())
+        .map_err(|_| "Settings are unavailable".to_string())
+}
+
+/// Set the speed (songs/min) and window (seconds) below which a slow download is reported
+#[tauri::command]
+fn set_slow_download_config(
+    app: AppHandle,
+    config: SlowDownloadConfig,
+    settings: tauri::State<Mutex<Settings>>,
+) -> Result<(), String> {
+    config.validate()?;
+
+    let mut settings = settings.lock().map_err(|_| "Settings are unavailable".to_string())?;
+    settings.slow_download = config;
+    settings::save(&app, &settings)
+}
+
+/// Load the saved settings, defaults when none were saved yet
+#[tauri::command]
+fn load_settings(app: AppHandle, settings: tauri::State<Mutex<Settings>>) -> Result<Settings, String> {
+    let loaded = settings::load(&app)?;
+    if let Ok(mut settings) = settings.lock() {
+        *settings = loaded.clone();
+    }
+    Ok(loaded)
+}
+
+/// Save the settings so they are restored on the next launch
+#[tauri::command]
+fn save_settings(
+    app: AppHandle,
+    settings: Settings,
+    store: tauri::State<Mutex<Settings>>,
+) -> Result<(), String> {
+    settings.validate()?;
+    settings::save(&app, &settings)?;
+    if let Ok(mut stored) = store.lock() {
+        *stored = settings;
+    }
+    Ok(())
+}
+
+/// Repair the settings file: fill missing fields, drop unknown ones and rewrite it
+#[tauri::command]
+fn repair_settings(
+    app: AppHandle,
+    settings: tauri::State<Mutex<Settings>>,
+) -> Result<settings::RepairReport, String> {
+    let (repaired, report) = settings::repair(&app)?;
+    if let Ok(mut settings) = settings.lock() {
+        *settings = repaired;
+    }
+    Ok(report)
+}
+
+/// What a Spotify link points to, so the UI can pick the content type itself
+#[tauri::command]
+fn detect_content_type(url: String) -> Result<ContentKind, String> {
+    validate_spotify_url(&url)
+}
+
+/// A pasted link in its canonical form, the one to hand to download_content
+#[tauri::command]
+fn normalize_url(raw: String) -> Result<String, String> {
+    normalize_spotify_url(&raw)
+}
+
+/// Add a download to the end of the queue and return its id
+#[tauri::command]
+fn enqueue_download(
+    app: AppHandle,
+    url: String,
+    options: Option<DownloadOptions>,
+) -> Result<u64, String> {
+    let kind = validate_spotify_url(&url)?;
+    let options = options.unwrap_or_default();
+    if kind == ContentKind::Artist && options.expected_tracks.is_none() {
+        return Err(ARTIST_PREVIEW_MESSAGE.to_string());
+    }
+    Ok(queue::enqueue(&app, url.clone(), vec![url], kind, options, None))
+}
+
+/// Read a text file of Spotify links, one per line, for adding to the queue
+#[tauri::command]
+fn import_url_list(file_path: String) -> Result<UrlImport, String> {
+    let contents = fs::read_to_string(&file_path)
+        .map_err(|e| format!("Failed to read {}: {}", file_path, e))?;
+    Ok(queue::parse_url_list(&contents))
+}
+
+/// Remove a pending download from the queue
+#[tauri::command]
+fn dequeue_download(id: u64, queue: tauri::State<DownloadQueue>) -> Result<(), String> {
+    queue.remove(id)
+}
+
+/// Move a pending download to another position among the pending ones
+#[tauri::command]
+fn move_queued_download(id: u64, position: usize, queue: tauri::State<DownloadQueue>) -> Result<(), String> {
+    queue.move_to(id, position)
+}
+
+/// The active download followed by the pending ones, in order
+#[tauri::command]
+fn list_queue(queue: tauri::State<DownloadQueue>) -> Vec<QueuedDownload> {
+    queue.list()
+}
+
+/// Re-send the last progress with a new message, so a paused bar doesn't look stuck
+fn emit_pause_state(app: &AppHandle, state: &DownloadState, message: &str) {
+    let last = state.last_progress.lock().ok().and_then(|last| last.clone());
+    if let Some(payload) = last {
+        emit_progress(app, ProgressPayload { message: message.to_string(), eta_seconds: None, ..payload });
+    }
+}
+
+/// Suspend the running download until resume_download
+#[tauri::command]
+fn pause_download(app: AppHandle, state: tauri::State<DownloadState>) -> Result<(), String> {
+    // Windows has no SIGSTOP; suspending there would need the debug API on every spotdl/ffmpeg thread
+    if cfg!(not(unix)) {
+        return Err("Pausing is only supported on Linux and macOS".to_string());
+    }
+    if state.pause_reasons.load(Ordering::SeqCst) & PAUSE_USER != 0 {
+        return Err("Download is already paused".to_string());
+    }
+    if !pause_child(&state, PAUSE_USER) {
+        return Err("No active download to pause".to_string());
+    }
+
+    emit_pause_state(&app, &state, "Paused");
+    Ok(())
+}
+
+/// Continue a download paused with pause_download
+#[tauri::command]
+fn resume_download(app: AppHandle, state: tauri::State<DownloadState>) -> Result<(), String> {
+    // The drive may have been unplugged while the download was paused
+    if state.pause_reasons.load(Ordering::SeqCst) & PAUSE_USER != 0 {
+        if let Some(dir) = lock_recovering(&state.output_dir).clone() {
+            disk_space::check_drive(&dir)?;
+            disk_space::check_writable(&dir)?;
+        }
+    }
+    if !resume_child(&state, PAUSE_USER) {
+        return Err("Download is not paused".to_string());
+    }
+
+    // Quiet hours or the thermal guard may still be holding it
+    let message = if state.pause_reasons.load(Ordering::SeqCst) == 0 {
+        "Downloading..."
+    } else {
+        "Still paused by quiet hours or the thermal guard"
+    };
+    emit_pause_state(&app, &state, message);
+    Ok(())
+}
+
+/// Cancel the current download
+#[tauri::command]
+fn cancel_download(state: tauri::State<DownloadState>) -> Result<(), String> {
+    if cancel_active(&state) {
+        Ok(())
+    } else {
+        Err("No active download to cancel".to_string())
+    }
+}
+
+/// Cancel whatever download is running, returns false when there is none. The
+/// download's own wait reaps spotdl, killing it if it ignores the terminate
+fn cancel_active(state: &DownloadState) -> bool {
+    let pid = state.child_pid.load(Ordering::SeqCst);
+    if pid == 0 && state.busy.load(Ordering::SeqCst) {
+        // Between two spotdl runs, e.g. just after the song list was looked up.
+        // The download checks the flag before it starts the next one
+        state.is_cancelled.store(true, Ordering::SeqCst);
+        true
+    } else if pid > 0 {
+        state.is_cancelled.store(true, Ordering::SeqCst);
+        
+        terminate_process(pid);
+        
+        // A stopped process only handles the TERM once it is continued
+        if state.pause_reasons.swap(0, Ordering::SeqCst) != 0 {
+            send_signal(pid, "CONT");
+        }
+        
+        state.child_pid.store(0, Ordering::SeqCst);
+        true
+    } else {
+        false
+    }
+}
+
+#[derive(Clone, Serialize)]
+struct QueueCancelledPayload {
+    removed: u32,  // pending downloads that were dropped
+    active_cancelled: bool,
+}
+
+/// Stop everything: empty the queue and cancel the running download
+#[tauri::command]
+fn cancel_all(app: AppHandle, state: tauri::State<DownloadState>) -> QueueCancelledPayload {
+    // The queue first, so the worker doesn't start the next download once this one ends
+    let removed = queue::cancel_all(&app);
+    let payload = QueueCancelledPayload { removed, active_cancelled: cancel_active(&state) };
+    let _ = app.emit("queue-cancelled", payload.clone());
+    payload
+}
+
+/// Stop the song that is downloading right now and go on with the next one.
+/// Only per-track downloads run a separate spotdl for each song
+#[tauri::command]
+fn skip_current_track(state: tauri::State<DownloadState>) -> Result<(), String> {
+    let pid = state.child_pid.load(Ordering::SeqCst);
+    if pid == 0 {
+        return Err("No active download to skip in".to_string());
+    }
+    if !state.per_track.load(Ordering::SeqCst) {
+        return Err("Only downloads started one track at a time can skip a track".to_string());
+    }
+
+    state.skip_requested.store(true, Ordering::SeqCst);
+    terminate_process(pid);
+
+    // A stopped process only handles the TERM once it is continued
+    if state.pause_reasons.load(Ordering::SeqCst) != 0 {
+        send_signal(pid, "CONT");
+    }
+    Ok(())
+}
+
+// Progress shared between the stdout and stderr reader threads of one download
+struct DownloadSession {
+    current_track: Mutex<u32>,
+    total_tracks: Mutex<u32>,
+    last_permille: Mutex<u32>,
+    start_time: Instant,
+    missing_lyrics: Mutex<Vec<String>>,
+    region_locked: Mutex<Vec<String>>,
+    slow_since: Mutex<Option<Instant>>,
+    slow_warned: AtomicBool,
+    fail_fast: bool,
+    first_failure: Mutex<Option<String>>,
+    failed_tracks: Mutex<Vec<String>>,
+    format: String,
+    recent_errors: Mutex<VecDeque<String>>,
+    log: Option<LogFile>,
+    secrets: Vec<String>,  // hidden from every line we pass on
+    bytes_per_sec: Mutex<Option<u64>>,
+    ffmpeg_missing: AtomicBool,
+    downloaded: AtomicU32,
+    skipped: AtomicU32,  // includes files whose metadata was updated in place
+    fixed_total: bool,  // the song count is known up front, spotdl's "Found" lines don't change it
+    current_title: Mutex<Option<String>>,
+    timed_out: AtomicBool,
+    manifest: Option<Mutex<manifest::Recorder>>,
+    converting: AtomicU32,  // songs whose conversion has started but not finished
+    last_progress_emit: Mutex<Option<Instant>>,
+    pending_progress: Mutex<Option<ProgressPayload>>,  // held back by the throttle, sent once its interval is over
+    resume: Option<Mutex<resume::Tracker>>,
+    error_kind: Mutex<Option<ErrorKind>>,  // what the errors so far point to
+    updated: AtomicU32,  // files whose metadata was updated in place, also counted as skipped
+    removed: AtomicU32,  // files a sync deleted
+    match_warnings: Mutex<Vec<MatchWarning>>,
+    transient_failure: AtomicBool,  // the current spotdl run hit an error worth retrying
+    recovered: AtomicU32,  // songs that downloaded on a retry
+    speed_samples: Mutex<VecDeque<(Instant, u32)>>,  // downloaded count over the last PEAK_WINDOW
+    peak_songs_per_min: Mutex<Option<f64>>,
+}
+
+impl DownloadSession {
+    fn new(fail_fast: bool, format: &str) -> Self {
+        Self {
+            current_track: Mutex::new(0),
+            total_tracks: Mutex::new(1),
+            last_permille: Mutex::new(START_PERMILLE),
+            start_time: Instant::now(),
+            missing_lyrics: Mutex::new(Vec::new()),
+            region_locked: Mutex::new(Vec::new()),
+            slow_since: Mutex::new(None),
+            slow_warned: AtomicBool::new(false),
+            fail_fast,
+            first_failure: Mutex::new(None),
+            failed_tracks: Mutex::new(Vec::new()),
+            format: format.to_string(),
+            recent_errors: Mutex::new(VecDeque::new()),
+            log: None,
+            secrets: Vec::new(),
+            bytes_per_sec: Mutex::new(None),
+            ffmpeg_missing: AtomicBool::new(false),
+            downloaded: AtomicU32::new(0),
+            skipped: AtomicU32::new(0),
+            fixed_total: false,
+            current_title: Mutex::new(None),
+            timed_out: AtomicBool::new(false),
+            manifest: None,
+            converting: AtomicU32::new(0),
+            last_progress_emit: Mutex::new(None),
+            pending_progress: Mutex::new(None),
+            resume: None,
+            error_kind: Mutex::new(None),
+            updated: AtomicU32::new(0),
+            removed: AtomicU32::new(0),
+            match_warnings: Mutex::new(Vec::new()),
+            transient_failure: AtomicBool::new(false),
+            recovered: AtomicU32::new(0),
+            speed_samples: Mutex::new(VecDeque::new()),
+            peak_songs_per_min: Mutex::new(None),
+        }
+    }
+
+    /// Whether a progress update may go out now, at most one per PROGRESS_INTERVAL
+    /// unless `force` is set. Claims the slot when it returns true
+    fn progress_due(&self, force: bool) -> bool {
+        let mut last = lock_recovering(&self.last_progress_emit);
+        let now = Instant::now();
+        if force || last.is_none_or(|at| now.duration_since(at) >= PROGRESS_INTERVAL) {
+            *last = Some(now);
+            true
+        } else {
+            false
+        }
+    }
+
+    fn counts(&self) -> TrackCounts {
+        TrackCounts {
+            downloaded: self.downloaded.load(Ordering::SeqCst),
+            skipped: self.skipped.load(Ordering::SeqCst),
+            failed: self.failed_tracks.lock().map(|f| f.len() as u32).unwrap_or(0),
+            recovered: self.recovered.load(Ordering::SeqCst),
+        }
+    }
+
+    /// Record a finished download at `now` and raise the peak pace if the last PEAK_WINDOW
+    /// was the fastest yet. Skipped songs are left out, they take no time at all
+    fn note_downloaded(&self, now: Instant) {
+        let count = self.downloaded.load(Ordering::SeqCst);
+        let mut samples = lock_recovering(&self.speed_samples);
+        samples.push_back((now, count));
+        // Keep one sample at least PEAK_WINDOW old, so the span covers a whole window
+        while samples.get(1).is_some_and(|(at, _)| now.duration_since(*at) >= PEAK_WINDOW) {
+            samples.pop_front();
+        }
+
+        let Some(&(since, start)) = samples.front() else { return };
+        let span = now.duration_since(since);
+        if span >= PEAK_WINDOW {
+            let rate = songs_per_min(count - start, span.as_secs_f64());
+            let mut peak = lock_recovering(&self.peak_songs_per_min);
+            if rate > *peak {
+                *peak = rate;
+            }
+        }
+    }
+
+    /// Counts and speeds for the "download-summary" event
+    fn summary_event(&self) -> SummaryEvent {
+        // Downloaded songs only, like the peak, so skipped ones don't make it look faster
+        let downloaded = self.downloaded.load(Ordering::SeqCst);
+        let average = songs_per_min(downloaded, self.start_time.elapsed().as_secs_f64());
+        SummaryEvent {
+            counts: self.counts(),
+            average_songs_per_min: average,
+            // Too short for a full window, the average is the best there is
+            peak_songs_per_min: (*lock_recovering(&self.peak_songs_per_min)).or(average),
+        }
+    }
+
+    fn current_title(&self) -> Option<String> {
+        self.current_title.lock().ok().and_then(|title| title.clone())
+    }
+
+    fn set_current_title(&self, title: &str) {
+        if let Ok(mut current) = self.current_title.lock() {
+            *current = Some(title.to_string());
+        }
+    }
+
+    /// Mask configured credentials, in case spotdl prints them
+    fn redact(&self, line: String) -> String {
+        self.secrets.iter().fold(line, |line, secret| line.replace(secret.as_str(), "***"))
+    }
+
+    fn log_line(&self, stream: &str, line: &str) {
+        if let Some(log) = &self.log {
+            log.write_line(stream, line);
+        }
+    }
+}
+
+/// Sent as "download-summary" once a download ends
+#[derive(Clone, Serialize)]
+struct SummaryEvent {
+    #[serde(flatten)]
+    counts: TrackCounts,
+    average_songs_per_min: Option<f64>,
+    peak_songs_per_min: Option<f64>,
+}
+
+/// How many songs a download fetched, skipped and failed
+#[derive(Clone, Copy, Serialize)]
+struct TrackCounts {
+    downloaded: u32,
+    skipped: u32,
+    failed: u32,
+    recovered: u32,  // downloaded only after a retry, also counted as downloaded
+}
+
+impl std::fmt::Display for TrackCounts {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{} downloaded, {} skipped, {} failed", self.downloaded, self.skipped, self.failed)?;
+        if self.recovered > 0 {
+            write!(f, " ({} recovered after retry)", self.recovered)?;
+        }
+        Ok(())
+    }
+}
+
+/// What a successful or cancelled download returns: the status message, track counts and output folder
+#[derive(Clone, Serialize)]
+struct DownloadSummary {
+    message: String,
+    #[serde(flatten)]
+    counts: TrackCounts,
+    output_folder: String,  // where the songs went, a playlist's own subfolder when one was made
+    cancelled: bool,  // stopped by the user, the counts say what was saved before that
+    match_warnings: Vec<MatchWarning>,  // songs worth checking by ear
+}
+
+/// A song spotdl matched with low confidence, so the audio may be the wrong song
+#[derive(Clone, Serialize)]
+struct MatchWarning {
+    track: String,
+    score: Option<f64>,  // out of 100, when spotdl gave one
+}
+
+/// Point at the download's log file from its result message, if one was written
+fn with_log_path(message: String, log_path: Option<&Path>) -> String {
+    match log_path {
+        Some(path) => format!("{} (log: {})", message, path.display()),
+        None => message,
+    }
+}
+
+#[derive(Clone, Serialize)]
+struct LyricsStatus {
+    track: String,
+    plain: bool,
+    synced: bool,
+}
+
+#[derive(Clone, Serialize)]
+struct DownloadStartedPayload {
+    url: String,
+    content_type: String,
+    fail_fast: bool,
+}
+
+/// Sent in per-track mode when the spotdl run for one song starts
+#[derive(Clone, Serialize)]
+struct TrackStartedPayload {
+    track: String,
+    index: u32,  // 1-based
+    total: u32,
+}
+
+/// Sent when ffmpeg starts converting a song, so long conversions don't look frozen
+#[derive(Clone, Serialize)]
+struct TrackConvertingPayload {
+    track: Option<String>,
+    index: u32,  // 1-based
+    total: u32,
+}
+
+/// Sent in per-track mode while waiting out the pause before the next song
+#[derive(Clone, Serialize)]
+struct ThrottlingPayload {
+    next_track: String,
+    index: u32,  // 1-based, of the next song
+    total: u32,
+    delay_ms: u32,
+}
+
+#[derive(Clone, Serialize)]
+struct ConcurrencyPayload {
+    threads: u32,
+}
+
+#[derive(Clone, Serialize)]
+struct DownloadFinishedPayload {
+    success: bool,
+    save_file: Option<String>,
+    region_locked: Vec<String>,
+    album_folders: Vec<String>,
+    m3u_file: Option<String>,
+    error_kind: Option<ErrorKind>,  // why a failed download failed, when that is known
+}
+
+// Providers accepted by spotdl's --lyrics flag
+const LYRICS_PROVIDERS: &[&str] = &["genius", "musixmatch", "azlyrics", "synced"];
+
+// Name for --m3u, spotdl fills in the playlist or album name
+const M3U_TEMPLATE: &str = "{list[0]}.m3u8";
+
+// Per-track retries after a temporary error, waiting RETRY_BASE_DELAY and doubling up to RETRY_MAX_DELAY
+const MAX_RETRIES: u32 = 5;
+const RETRY_BASE_DELAY: Duration = Duration::from_secs(2);
+const RETRY_MAX_DELAY: Duration = Duration::from_secs(30);
+
+// A ramped-up download runs this many songs per thread before doubling its threads
+const RAMP_BATCH_ROUNDS: usize = 2;
+
+// Upper bound for the pause between per-track runs
+const MAX_TRACK_DELAY_MS: u32 = 60_000;
+
+// Values accepted by spotdl's --overwrite flag
+const OVERWRITE_POLICIES: &[&str] = &["skip", "force", "metadata"];
+
+// Audio sources accepted by spotdl's --audio flag
+const AUDIO_PROVIDERS: &[&str] = &["youtube", "youtube-music", "slider-kz", "soundcloud", "bandcamp", "piped"];
+
+// Most extra spotdl arguments one download may pass through
+const MAX_EXTRA_ARGS: usize = 16;
+
+// Output layout for playlists split into one folder per album
+const ALBUM_OUTPUT_TEMPLATE: &str = "{album}/{artists} - {title}.{output-ext}";
+const UNKNOWN_ALBUM_DIR: &str = "Unknown Album";
+
+// A custom --output template needs one of these, or every song gets written to the same file
+const UNIQUE_TEMPLATE_KEYS: &[&str] = &["{title}", "{trackid}"];
+
+fn validate_output_template(template: &str) -> Result<(), String> {
+    if !UNIQUE_TEMPLATE_KEYS.iter().any(|key| template.contains(key)) {
+        return Err(format!(
+            "Output template '{}' must contain {} so songs don't overwrite each other",
+            template,
+            UNIQUE_TEMPLATE_KEYS.join(" or ")
+        ));
+    }
+    Ok(())
+}
+
+// Values accepted by spotdl's --bitrate: fixed rates, VBR quality levels 0-9, or auto/disable
+const BITRATES: &[&str] = &[
+    "auto", "disable",
+    "8k", "16k", "24k", "32k", "40k", "48k", "64k", "80k", "96k",
+    "112k", "128k", "160k", "192k", "224k", "256k", "320k",
+    "0", "1", "2", "3", "4", "5", "6", "7", "8", "9",
+];
+
+// Formats spotdl can convert to, also the extensions of the audio files it writes
+const AUDIO_FORMATS: &[&str] = &["mp3", "flac", "opus", "m4a", "ogg", "wav"];
+
+/// YouTube serves AAC and Opus audio, every other format is re-encoded by ffmpeg
+fn needs_transcoding(format: &str) -> bool {
+    !matches!(format, "m4a" | "opus")
+}
+
+/// The bitrate a download of `format` gets when none was chosen: the highest rate spotdl
+/// offers for lossy formats it re-encodes, None for lossless ones and for m4a and opus,
+/// which are kept as YouTube serves them
+fn default_bitrate_for_format(format: &str) -> Option<&'static str> {
+    match format {
+        "mp3" | "ogg" => Some("320k"),
+        _ => None,
+    }
+}
+
+#[derive(Clone, Serialize)]
+struct BitratePreset {
+    format: &'static str,
+    bitrate: Option<&'static str>,
+}
+
+/// The default bitrate of every format, so the UI can say what "Default" means
+#[tauri::command]
+fn bitrate_presets() -> Vec<BitratePreset> {
+    AUDIO_FORMATS.iter()
+        .map(|&format| BitratePreset { format, bitrate: default_bitrate_for_format(format) })
+        .collect()
+}
+
+#[derive(Clone, Serialize)]
+struct SlowDownloadPayload {
+    songs_per_min: f64,
+    suggestion: String,
+}
+
+/// Warn once per download when the average speed stays under the configured minimum
+fn check_slow_download(app: &AppHandle, session: &DownloadSession, current: u32, elapsed_secs: f64) {
+    if session.slow_warned.load(Ordering::SeqCst) {
+        return;
+    }
+    let Some(config) = app.state::<Mutex<Settings>>().lock().ok().map(|s| s.slow_download.clone()) else { return };
+    let Ok(mut slow_since) = session.slow_since.lock() else { return };
+
+    let window_secs = config.window_secs as f64;
+    let songs_per_min = if elapsed_secs > 0.0 { (current as f64 / elapsed_secs) * 60.0 } else { 0.0 };
+    if elapsed_secs < window_secs || songs_per_min >= config.min_songs_per_min {
+        *slow_since = None;
+        return;
+    }
+
+    let since = *slow_since.get_or_insert_with(Instant::now);
+    if since.elapsed().as_secs_f64() >= window_secs {
+        session.slow_warned.store(true, Ordering::SeqCst);
+        let _ = app.emit("slow-download", SlowDownloadPayload {
+            songs_per_min,
+            suggestion: "Downloads are unusually slow. Try a different audio provider or check your network connection.".to_string(),
+        });
+    }
+}
+
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum TrackStatus {
+    Downloaded,
+    Skipped,
+    Updated,
+    Failed,
+}
+
+#[derive(Clone, Serialize)]
+struct TrackCompletePayload {
+    title: String,
+    artist: String,
+    index: u32,
+    total: u32,
+    status: TrackStatus,
+}
+
+impl TrackCompletePayload {
+    /// Build the payload from a spotdl "Artist - Title" label
+    fn new(label: &str, index: u32, total: u32, status: TrackStatus) -> Self {
+        let (artist, title) = match label.split_once(" - ") {
+            Some((artist, title)) => (artist.trim(), title.trim()),
+            None => ("", label.trim()),
+        };
+
+        Self {
+            title: title.to_string(),
+            artist: artist.to_string(),
+            index,
+            total: total.max(index),
+            status,
+        }
+    }
+}
+
+// What a single spotdl event changes in the UI
+#[derive(Default)]
+struct ProgressUpdate {
+    progress: Option<ProgressPayload>,
+    track: Option<TrackCompletePayload>,
+    converting: Option<TrackConvertingPayload>,
+}
+
+/// Average pace of `current` songs in `elapsed_secs`, None before the first one is done
+fn songs_per_min(current: u32, elapsed_secs: f64) -> Option<f64> {
+    (current > 0 && elapsed_secs > 0.0).then(|| current as f64 / elapsed_secs * 60.0)
+}
+
+/// The average pace for the UI: songs/min, or seconds per song when that's under one
+fn compute_speed(current: u32, elapsed_secs: f64) -> String {
+    match songs_per_min(current, elapsed_secs) {
+        None => "calculating...".to_string(),
+        Some(rate) if rate >= 1.0 => format!("{:.1} songs/min", rate),
+        Some(rate) => format!("{:.0}s/song", 60.0 / rate),
+    }
+}
+
+// Peak speed is the best pace over a span this long, shorter bursts say little
+const PEAK_WINDOW: Duration = Duration::from_secs(30);
+
+/// Seconds left at the average pace so far. The total starts out as a
+/// placeholder of 1, so a count past it means spotdl never reported one
+fn eta_seconds(current: u32, total: u32, elapsed_secs: f64) -> Option<u32> {
+    if current == 0 || current >= total {
+        return None;
+    }
+
+    let secs_per_song = elapsed_secs / current as f64;
+    Some((secs_per_song * (total - current) as f64).round() as u32)
+}
+
+// Where the bar starts for every download, before spotdl reports anything, in permille
+const START_PERMILLE: u32 = 50;
+
+// Shortest gap between progress updates from spotdl's output, more only make the UI stutter
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Move the bar to `permille` unless it is already further along, so progress
+/// never goes backwards within a download. Returns the permille to show
+fn advance_permille(last: &mut u32, permille: u32) -> u32 {
+    *last = (*last).max(permille);
+    *last
+}
+
+/// The "Artist - Title" an event is about, if the line named one
+fn event_title(event: &SpotdlEvent) -> Option<&str> {
+    let title = match event {
+        SpotdlEvent::Downloaded { track }
+        | SpotdlEvent::Skipped { track }
+        | SpotdlEvent::MetadataUpdated { track }
+        | SpotdlEvent::Failed { track, .. }
+        | SpotdlEvent::MissingLyrics { track }
+        | SpotdlEvent::LowConfidenceMatch { track, .. } => track.as_str(),
+        SpotdlEvent::Converting { track } => track.as_deref()?,
+        _ => return None,
+    };
+    Some(title).filter(|title| !title.is_empty())
+}
+
+/// Lock a progress counter even if a reader thread panicked while holding it. The
+/// counters are plain numbers that stay valid, and giving up on them would freeze the bar
+fn lock_recovering<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Advance the session's counters for one event and work out what to emit
+fn apply_event(session: &DownloadSession, event: &SpotdlEvent) -> ProgressUpdate {
+    let mut update = ProgressUpdate::default();
+
+    let mut current = lock_recovering(&session.current_track);
+    let mut total = lock_recovering(&session.total_tracks);
+    let mut last = lock_recovering(&session.last_permille);
+
+    // Lines without a song name keep showing the last one
+    if let Some(title) = event_title(event) {
+        session.set_current_title(title);
+    }
+
+    match event {
+        // Per-track runs each report finding a single song
+        SpotdlEvent::Found { .. } if session.fixed_total => {}
+        SpotdlEvent::Found { count } => {
+            *total = (*count).max(1);
+            let permille = advance_permille(&mut last, 100);
+            update.progress = Some(ProgressPayload {
+                permille,
+                percent: permille / 10,
+                message: format!("Found {} song(s), starting download...", *total),
+                current_track: 0,
+                total_tracks: *total,
+                speed: "".to_string(),
+                eta_seconds: None,
+                bytes_per_sec: None,
+                current_title: session.current_title(),
+            });
+        }
+        SpotdlEvent::Downloaded { track } | SpotdlEvent::Skipped { track } | SpotdlEvent::MetadataUpdated { track } => {
+            *current += 1;
+            let (status, message) = match event {
+                SpotdlEvent::Downloaded { .. } => (TrackStatus::Downloaded, "Downloading..."),
+                SpotdlEvent::MetadataUpdated { .. } => (TrackStatus::Updated, "Updating metadata..."),
+                _ => (TrackStatus::Skipped, "Processing..."),
+            };
+            let counter = if matches!(event, SpotdlEvent::Downloaded { .. }) { &session.downloaded } else { &session.skipped };
+            counter.fetch_add(1, Ordering::SeqCst);
+            // Downloaded is printed once a song's conversion is done, a skip ends it too
+            if !matches!(event, SpotdlEvent::MetadataUpdated { .. }) {
+                let _ = session.converting.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1));
+            }
+            if matches!(event, SpotdlEvent::Downloaded { .. }) {
+                session.note_downloaded(Instant::now());
+            }
+
+            if !track.is_empty() {
+                let index = *current + session.failed_tracks.lock().map(|f| f.len() as u32).unwrap_or(0);
+                update.track = Some(TrackCompletePayload::new(track, index, *total, status));
+            }
+
+            // Calculate progress: 10% for finding, 10-95% for downloading, songs still converting count as half
+            let done = *current as f32 + session.converting.load(Ordering::SeqCst) as f32 / 2.0;
+            let download_progress = ((done / *total as f32) * 850.0) as u32;
+            let previous = *last;
+            let permille = advance_permille(&mut last, (100 + download_progress).min(950));
+            if permille > previous {
+                update.progress = Some(ProgressPayload {
+                    permille,
+                    percent: permille / 10,
+                    message: message.to_string(),
+                    current_track: *current,
+                    total_tracks: *total,
+                    speed: compute_speed(*current, session.start_time.elapsed().as_secs_f64()),
+                    eta_seconds: eta_seconds(*current, *total, session.start_time.elapsed().as_secs_f64()),
+                    bytes_per_sec: session.bytes_per_sec.lock().ok().and_then(|rate| *rate),
+                    current_title: session.current_title(),
+                });
+            }
+        }
+        SpotdlEvent::Failed { track, .. } => {
+            // A song can fail while it converts
+            let _ = session.converting.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1));
+            if let Ok(mut failed) = session.failed_tracks.lock() {
+                failed.push(track.clone());
+                let index = *current + failed.len() as u32;
+                update.track = Some(TrackCompletePayload::new(track, index, *total, TrackStatus::Failed));
+            }
+        }
+        SpotdlEvent::Converting { track } => {
+            let converting = session.converting.fetch_add(1, Ordering::SeqCst) + 1;
+            let index = *current + converting;
+            update.converting = Some(TrackConvertingPayload { track: track.clone(), index, total: (*total).max(index) });
+
+            // A single track has nothing else to show while it converts. With more, songs
+            // being converted count as half done, so the bar keeps moving between finished ones
+            let (target, message) = if *total <= 1 {
+                (900, format!("Converting to {}...", session.format.to_uppercase()))
+            } else {
+                let done = *current as f32 + converting as f32 / 2.0;
+                (
+                    (100 + ((done / *total as f32) * 850.0) as u32).min(950),
+                    format!("Converting song {} of {} to {}...", index, *total, session.format.to_uppercase()),
+                )
+            };
+            let permille = advance_permille(&mut last, target);
+            update.progress = Some(ProgressPayload {
+                permille,
+                percent: permille / 10,
+                message,
+                current_track: *current,
+                total_tracks: *total,
+                speed: compute_speed(*current, session.start_time.elapsed().as_secs_f64()),
+                eta_seconds: eta_seconds(*current, *total, session.start_time.elapsed().as_secs_f64()),
+                bytes_per_sec: None,
+                current_title: session.current_title(),
+            });
+        }
+        SpotdlEvent::Transfer { bytes_per_sec } => {
+            if let Ok(mut rate) = session.bytes_per_sec.lock() {
+                *rate = Some(*bytes_per_sec);
+            }
+
+            // Single songs have no other progress while transferring, so refresh the bar
+            update.progress = Some(ProgressPayload {
+                permille: *last,
+                percent: *last / 10,
+                message: "Downloading...".to_string(),
+                current_track: *current,
+                total_tracks: *total,
+                speed: compute_speed(*current, session.start_time.elapsed().as_secs_f64()),
+                eta_seconds: eta_seconds(*current, *total, session.start_time.elapsed().as_secs_f64()),
+                bytes_per_sec: Some(*bytes_per_sec),
+                current_title: session.current_title(),
+            });
+        }
+        SpotdlEvent::MissingLyrics { .. }
+        | SpotdlEvent::LowConfidenceMatch { .. }
+        | SpotdlEvent::FfmpegMissing
+        | SpotdlEvent::Removed { .. }
+        | SpotdlEvent::Other => {}
+    }
+
+    update
+}
+
+// How many stderr error lines a failed download quotes
+const ERROR_LINES_KEPT: usize = 3;
+
+#[derive(Clone, Serialize)]
+struct DownloadLogPayload {
+    line: String,
+    severity: Severity,
+}
+
+/// Forward a raw stderr line to the UI's log pane, remembering the latest errors
+fn emit_log_line(line: &str, app: &AppHandle, session: &DownloadSession) {
+    if line.trim().is_empty() {
+        return;
+    }
+
+    let (severity, message) = parser::log_severity(line);
+    if severity == Severity::Error {
+        if let Ok(mut errors) = session.recent_errors.lock() {
+            if errors.len() == ERROR_LINES_KEPT {
+                errors.pop_front();
+            }
+            errors.push_back(message.to_string());
+        }
+
+        // Songs without a match are common in downloads that fail for another reason
+        if let Some(kind) = parser::classify_error(line) {
+            let mut error_kind = lock_recovering(&session.error_kind);
+            if error_kind.is_none_or(|known| known == ErrorKind::NoMatch) {
+                *error_kind = Some(kind);
+            }
+        }
+    }
+
+    let _ = app.emit("download-log", DownloadLogPayload { line: line.to_string(), severity });
+}
+
+/// Helper function to process output lines
+fn process_output_line(line: &str, app: &AppHandle, session: &DownloadSession) {
+    let Some(event) = parser::parse_line(line) else { return };
+    if parser::is_transient_error(line) {
+        session.transient_failure.store(true, Ordering::SeqCst);
+    }
+
+    match &event {
+        // Remember tracks spotdl couldn't find lyrics for
+        SpotdlEvent::MissingLyrics { track } => {
+            if let Ok(mut missing) = session.missing_lyrics.lock() {
+                missing.push(track.clone());
+            }
+        }
+        // Note the song's file in the folder's manifest, so the next download can leave it out,
+        // and in the saved copy of the download in case the app doesn't get to finish it
+        SpotdlEvent::Downloaded { track } | SpotdlEvent::Skipped { track } | SpotdlEvent::MetadataUpdated { track } => {
+            if let Some(Ok(mut recorder)) = session.manifest.as_ref().map(Mutex::lock) {
+                recorder.record(track);
+            }
+            if let Some(Ok(mut resume)) = session.resume.as_ref().map(Mutex::lock) {
+                resume.complete(track);
+            }
+            if matches!(event, SpotdlEvent::MetadataUpdated { .. }) {
+                session.updated.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+        SpotdlEvent::Removed { .. } => {
+            session.removed.fetch_add(1, Ordering::SeqCst);
+        }
+        // Flag songs the user may want to listen to, once each
+        SpotdlEvent::LowConfidenceMatch { track, score } => {
+            let mut warnings = lock_recovering(&session.match_warnings);
+            if !warnings.iter().any(|warning| &warning.track == track) {
+                let warning = MatchWarning { track: track.clone(), score: *score };
+                let _ = app.emit("match-warning", warning.clone());
+                warnings.push(warning);
+            }
+        }
+        // Conversion can't work at all, so the final error says so instead of a generic failure
+        SpotdlEvent::FfmpegMissing => session.ffmpeg_missing.store(true, Ordering::SeqCst),
+        SpotdlEvent::Failed { track, region_locked } => {
+            // Tracks that are unavailable in the user's region, kept apart from other failures
+            if *region_locked {
+                if let Ok(mut locked) = session.region_locked.lock() {
+                    if !locked.contains(track) {
+                        locked.push(track.clone());
+                    }
+                }
+            }
+
+            // Remember the first failed track, and stop right there in fail-fast mode
+            if let Ok(mut first_failure) = session.first_failure.lock() {
+                if first_failure.is_none() {
+                    *first_failure = Some(track.clone());
+                    if session.fail_fast {
+                        let pid = app.state::<DownloadState>().child_pid.load(Ordering::SeqCst);
+                        if pid > 0 {
+                            terminate_process(pid);
+                        }
+                    }
+                }
+            }
+        }
+        _ => {}
+    }
+
+    let update = apply_event(session, &event);
+    if let Some(track) = update.track {
+        let _ = app.emit("track-complete", track);
+    }
+    if let Some(converting) = update.converting {
+        let _ = app.emit("track-converting", converting);
+    }
+    if let Some(payload) = update.progress {
+        // Updates in between are coalesced into the latest one, but the song count being found,
+        // a single song starting to convert and the last song finishing always get through
+        let phase_change = match &event {
+            SpotdlEvent::Found { .. } => true,
+            SpotdlEvent::Converting { .. } => payload.total_tracks <= 1,
+            _ => payload.current_track >= payload.total_tracks,
+        };
+        if session.progress_due(phase_change) {
+            *lock_recovering(&session.pending_progress) = None;
+            emit_progress(app, payload);
+        } else {
+            // Still what a reloaded UI catches up to
+            *lock_recovering(&app.state::<DownloadState>().last_progress) = Some(payload.clone());
+            *lock_recovering(&session.pending_progress) = Some(payload);
+        }
+    }
+
+    let current = *lock_recovering(&session.current_track);
+    check_slow_download(app, session, current, session.start_time.elapsed().as_secs_f64());
+}
+
+/// Send the progress update the throttle held back, once its interval is over, so the
+/// UI catches up even when spotdl goes quiet after it
+fn flush_progress(app: &AppHandle, session: &DownloadSession) {
+    if lock_recovering(&session.pending_progress).is_none() || !session.progress_due(false) {
+        return;
+    }
+    let pending = lock_recovering(&session.pending_progress).take();
+    if let Some(payload) = pending {
+        emit_progress(app, payload);
+    }
+}
+
+/// The platform's Music folder (XDG_MUSIC_DIR, the Windows known folder, ~/Music on macOS),
+/// or the app data dir on systems that don't have one
+fn default_download_dir(app: &AppHandle) -> PathBuf {
+    app.path()
+        .audio_dir()
+        .or_else(|_| app.path().app_data_dir())
+        .unwrap_or_else(|_| PathBuf::from("."))
+}
+
+/// Get the default download path, creating it if needed
+#[tauri::command]
+fn get_download_path(app: AppHandle) -> String {
+    let music_path = default_download_dir(&app);
+
+    // Create directory if it doesn't exist
+    let _ = fs::create_dir_all(&music_path);
+
+    music_path.to_string_lossy().into_owned()
+}
+
+/// Bytes free on the drive a download folder is on
+#[tauri::command]
+fn get_free_space(path: String) -> Result<u64, String> {
+    disk_space::free_space(Path::new(&path))
+}
+
+/// The songs in a download folder with their embedded tags, for the library view
+#[tauri::command]
+async fn list_downloads(path: String) -> Result<Vec<DownloadedTrack>, String> {
+    let dir = Path::new(&path);
+    if !dir.is_dir() {
+        return Err(format!("Folder does not exist: {}", path));
+    }
+    Ok(library::list(dir))
+}
+
+/// Find downloaded songs that are empty, unreadable, cut short or, when ffmpeg is
+/// available, fail to decode, so they can be deleted and downloaded again
+#[tauri::command]
+async fn verify_downloads(app: AppHandle, path: String) -> Result<Vec<IntegrityIssue>, String> {
+    let dir = Path::new(&path);
+    if !dir.is_dir() {
+        return Err(format!("Folder does not exist: {}", path));
+    }
+
+    let has_ffmpeg = command_version(ffmpeg_command(&app).arg("-version")).is_some();
+    Ok(library::verify(dir, |file| {
+        if !has_ffmpeg {
+            return None;
+        }
+        // -v error prints nothing for a file that decodes cleanly
+        let output = ffmpeg_command(&app)
+            .args(["-nostdin", "-v", "error", "-i"])
+            .arg(file)
+            .args(["-f", "null", "-"])
+            .stdin(Stdio::null())
+            .output()
+            .ok()?;
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let first_error = stderr.lines().map(str::trim).find(|line| !line.is_empty());
+        match (output.status.success(), first_error) {
+            (true, None) => None,
+            (_, error) => Some(format!("ffmpeg can't decode it: {}", error.unwrap_or("unknown error"))),
+        }
+    }))
+}
+
+/// Move finished songs from a staging folder into the library, returns how many moved.
+/// Songs whose name is already taken in `to` are left in `from`
+#[tauri::command]
+async fn move_downloads(from: String, to: String, pattern: Option<String>) -> Result<u32, String> {
+    let source = Path::new(&from);
+    if !source.is_dir() {
+        return Err(format!("Folder does not exist: {}", from));
+    }
+    let target = Path::new(&to);
+    fs::create_dir_all(target).map_err(|e| format!("Failed to create {}: {}", to, e))?;
+
+    // Moving a folder into itself would only shuffle files around
+    let source = source.canonicalize().map_err(|e| format!("Failed to resolve {}: {}", from, e))?;
+    let target = target.canonicalize().map_err(|e| format!("Failed to resolve {}: {}", to, e))?;
+    if target.starts_with(&source) {
+        return Err("The library folder can't be the download folder or inside it".to_string());
+    }
+
+    let pattern = pattern.map(|p| p.trim().to_string()).filter(|p| !p.is_empty());
+    library::move_files(&source, &target, pattern.as_deref())
+}
+
+/// Delete a song from the download folder and forget it in the manifests of the folders
+/// above it, so the next download of its playlist fetches it again. Only audio files
+/// inside the configured download folder can be deleted
+#[tauri::command]
+fn delete_download(app: AppHandle, path: String) -> Result<(), String> {
+    let file = Path::new(&path);
+    if !file.is_file() {
+        return Err(format!("File does not exist: {}", path));
+    }
+    let is_audio = file.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| AUDIO_FORMATS.contains(&ext.to_lowercase().as_str()));
+    if !is_audio {
+        return Err(format!("Only downloaded songs can be deleted, {} is not an audio file", path));
+    }
+
+    let root = app.state::<Mutex<Settings>>()
+        .lock()
+        .ok()
+        .and_then(|settings| settings.download_path.clone())
+        .filter(|dir| !dir.trim().is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| default_download_dir(&app));
+    let root = root.canonicalize().map_err(|e| format!("Failed to resolve the download folder: {}", e))?;
+    let file = file.canonicalize().map_err(|e| format!("Failed to resolve {}: {}", path, e))?;
+    if !file.starts_with(&root) {
+        return Err(format!("{} is outside the download folder {}, it was left alone", path, root.display()));
+    }
+
+    delete_song(&root, &file)
+}
+
+/// Delete a song and its lyrics, after dropping it from the manifests of the folders
+/// above it inside `root` so a manifest that can't be written leaves the song in place
+fn delete_song(root: &Path, file: &Path) -> Result<(), String> {
+    for dir in file.ancestors().skip(1).take_while(|dir| dir.starts_with(root)) {
+        Manifest::forget_file(dir, file)?;
+    }
+    fs::remove_file(file).map_err(|e| format!("Failed to delete {}: {}", file.display(), e))?;
+    let _ = fs::remove_file(file.with_extension("lrc"));
+    Ok(())
+}
+
+/// Show a download folder in the system file manager, with its newest entry selected
+#[tauri::command]
+fn open_download_folder(app: AppHandle, path: String) -> Result<(), String> {
+    let dir = Path::new(&path);
+    if !dir.is_dir() {
+        return Err(format!("Download folder does not exist: {}", path));
+    }
+
+    match newest_entry(dir) {
+        Some(entry) => app.opener().reveal_item_in_dir(entry),
+        None => app.opener().open_path(path.clone(), None::<&str>),
+    }
+    .map_err(|e| format!("Failed to open download folder: {}", e))
+}
+
+/// Most recently modified file or folder directly inside `dir`
+fn newest_entry(dir: &Path) -> Option<PathBuf> {
+    fs::read_dir(dir)
+        .ok()?
+        .flatten()
+        .filter_map(|entry| Some((entry.metadata().ok()?.modified().ok()?, entry.path())))
+        .max_by_key(|(modified, _)| *modified)
+        .map(|(_, path)| path)
+}
+
+/// Make spotdl write its output as it happens: Python fully buffers stdout when it
+/// isn't a terminal, which would otherwise deliver all progress at the very end
+fn force_line_output(cmd: &mut Command) {
+    cmd.env("PYTHONUNBUFFERED", "1");
+}
+
+/// Find spotdl: the configured path first, then `PATH`, then the user's ~/.venv
+fn resolve_spotdl_path(configured: Option<&str>) -> Result<PathBuf, String> {
+    let mut searched = Vec::new();
+
+    if let Some(configured) = configured.map(str::trim).filter(|p| !p.is_empty()) {
+        let path = PathBuf::from(configured);
+        if path.is_file() {
+            return Ok(path);
+        }
+        searched.push(format!("configured path {}", configured));
+    }
+
+    let finder = if cfg!(windows) { "where" } else { "which" };
+    if let Ok(output) = Command::new(finder).arg("spotdl").output() {
+        let found = String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty())
+            .map(PathBuf::from);
+        if let Some(path) = found.filter(|_| output.status.success()) {
+            return Ok(path);
+        }
+    }
+    searched.push("PATH".to_string());
+
+    let venv_path = venv_spotdl_path();
+    if venv_path.is_file() {
+        return Ok(venv_path);
+    }
+    searched.push(venv_path.to_string_lossy().into_owned());
+
+    Err(format!("spotdl was not found. Searched: {}", searched.join(", ")))
+}
+
+fn home_dir() -> PathBuf {
+    let var = if cfg!(windows) { "USERPROFILE" } else { "HOME" };
+    PathBuf::from(std::env::var(var).unwrap_or_else(|_| ".".to_string()))
+}
+
+/// A program in the ~/.venv virtual environment, e.g. "spotdl" or "pip"
+fn venv_program(name: &str) -> PathBuf {
+    if cfg!(windows) {
+        home_dir().join(".venv").join("Scripts").join(format!("{}.exe", name))
+    } else {
+        home_dir().join(".venv").join("bin").join(name)
+    }
+}
+
+/// Where spotdl lives when installed into a ~/.venv virtual environment
+fn venv_spotdl_path() -> PathBuf {
+    venv_program("spotdl")
+}
+
+/// spotdl path for the current settings
+fn spotdl_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let configured = app.state::<Mutex<Settings>>()
+        .lock()
+        .ok()
+        .and_then(|settings| settings.spotdl_path.clone());
+    resolve_spotdl_path(configured.as_deref())
+}
+
+// Shown when ffmpeg is missing, both up front and when a download fails for lack of it
+const FFMPEG_INSTALL_HINT: &str =
+    "ffmpeg is required for conversion. Install it (or run `spotdl --download-ffmpeg`) and retry.";
+
+#[derive(Clone, Serialize)]
+struct DependencyStatus {
+    spotdl_found: bool,
+    spotdl_version: Option<String>,
+    ffmpeg_found: bool,
+    ffmpeg_version: Option<String>,
+    ffmpeg_hint: Option<String>,
+}
+
+/// Check that spotdl and ffmpeg can be run, and which versions they are
+#[tauri::command]
+fn check_dependencies(app: AppHandle) -> DependencyStatus {
+    let spotdl_version = spotdl_path(&app)
+        .ok()
+        .and_then(|path| command_version(spotdl_command(&app, &path).arg("--version")));
+    let ffmpeg_version = command_version(ffmpeg_command(&app).arg("-version"));
+
+    DependencyStatus {
+        spotdl_found: spotdl_version.is_some(),
+        spotdl_version,
+        ffmpeg_found: ffmpeg_version.is_some(),
+        ffmpeg_hint: ffmpeg_version.is_none().then(|| FFMPEG_INSTALL_HINT.to_string()),
+        ffmpeg_version,
+    }
+}
+
+/// Run a version query and parse its first line: "4.2.5", or the word after
+/// "version" for banners like "ffmpeg version 6.1.1 Copyright ..."
+fn command_version(cmd: &mut Command) -> Option<String> {
+    let output = cmd.stdin(Stdio::null()).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let first_line = stdout.lines().map(str::trim).find(|line| !line.is_empty())?;
+    let mut words = first_line.split_whitespace();
+    let version = match words.position(|word| word.eq_ignore_ascii_case("version")) {
+        Some(_) => words.next().unwrap_or(first_line),
+        None => first_line,
+    };
+    Some(version.to_string())
+}
+
+#[derive(Clone, Serialize)]
+struct AppInfo {
+    version: String,
+    spotdl_version: Option<String>,
+    os: String,
+    arch: String,
+}
+
+/// Which build is running and where, for the About dialog and bug reports
+#[tauri::command]
+fn get_app_version(app: AppHandle) -> AppInfo {
+    AppInfo {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        spotdl_version: spotdl_path(&app)
+            .ok()
+            .and_then(|path| command_version(spotdl_command(&app, &path).arg("--version"))),
+        os: std::env::consts::OS.to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+    }
+}
+
+/// Get the spotdl binary downloads will use
+#[tauri::command]
+fn get_spotdl_path(app: AppHandle) -> Result<String, String> {
+    spotdl_path(&app).map(|path| path.to_string_lossy().into_owned())
+}
+
+/// Use a specific spotdl binary (or `null` to search for it again)
+#[tauri::command]
+fn set_spotdl_path(
+    app: AppHandle,
+    path: Option<String>,
+    settings: tauri::State<Mutex<Settings>>,
+) -> Result<(), String> {
+    let path = path.map(|p| p.trim().to_string()).filter(|p| !p.is_empty());
+    if let Some(path) = &path {
+        if !Path::new(path).is_file() {
+            return Err(format!("spotdl was not found at {}", path));
+        }
+    }
+
+    let mut settings = settings.lock().map_err(|_| "Settings are unavailable".to_string())?;
+    settings.spotdl_path = path;
+    settings::save(&app, &settings)
+}
+
+// How install_spotdl can install spotdl
+const INSTALL_METHODS: &[&str] = &["venv", "pipx"];
+
+#[derive(Clone, Serialize)]
+struct InstallResult {
+    method: String,  // "venv" or "pipx"
+    spotdl_path: String,  // saved as the spotdl path, so downloads use this install
+    version: Option<String>,
+}
+
+/// Install or upgrade spotdl, either with pip into ~/.venv (created if needed) or
+/// with pipx. The installer's output is streamed as "setup-log" events
+#[tauri::command]
+async fn install_spotdl(
+    app: AppHandle,
+    method: Option<String>,
+    settings: tauri::State<'_, Mutex<Settings>>,
+) -> Result<InstallResult, String> {
+    let method = method.unwrap_or_else(|| "venv".to_string()).trim().to_lowercase();
+    if !INSTALL_METHODS.contains(&method.as_str()) {
+        return Err(format!("Unknown install method '{}'. Use {}", method, INSTALL_METHODS.join(" or ")));
+    }
+    let state = app.state::<DownloadState>();
+    let _busy = BusyGuard::acquire(&state)
+        .map_err(|_| "Wait for the current download to finish before installing spotdl".to_string())?;
+
+    let spotdl_path = if method == "venv" {
+        if !venv_program("pip").is_file() {
+            let python = if cfg!(windows) { "python" } else { "python3" };
+            let mut venv = Command::new(python);
+            venv.arg("-m").arg("venv").arg(home_dir().join(".venv"));
+            run_installer(&app, venv)?;
+        }
+        let mut pip = Command::new(venv_program("pip"));
+        pip.args(["install", "--upgrade", "spotdl"]);
+        run_installer(&app, pip)?;
+        venv_spotdl_path()
+    } else {
+        let mut pipx = Command::new("pipx");
+        pipx.args(["install", "--force", "spotdl"]);
+        run_installer(&app, pipx)?;
+        // pipx links its apps into ~/.local/bin on every platform
+        let name = if cfg!(windows) { "spotdl.exe" } else { "spotdl" };
+        home_dir().join(".local").join("bin").join(name)
+    };
+
+    if !spotdl_path.is_file() {
+        return Err(format!("The installer finished but spotdl is not at {}", spotdl_path.display()));
+    }
+    let version = command_version(spotdl_command(&app, &spotdl_path).arg("--version"));
+
+    let mut settings = settings.lock().map_err(|_| "Settings are unavailable".to_string())?;
+    settings.spotdl_path = Some(spotdl_path.to_string_lossy().into_owned());
+    settings::save(&app, &settings)?;
+
+    Ok(InstallResult { method, spotdl_path: spotdl_path.to_string_lossy().into_owned(), version })
+}
+
+/// Run one installer step, forwarding its stdout and stderr lines as "setup-log" events
+fn run_installer(app: &AppHandle, mut cmd: Command) -> Result<(), String> {
+    let program = cmd.get_program().to_string_lossy().into_owned();
+    if let Some(path) = search_path(app) {
+        cmd.env("PATH", path);
+    }
+    let mut child = cmd
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to run {}: {}", program, e))?;
+
+    let emit_lines = |app: AppHandle, stream: Box<dyn std::io::Read + Send>| {
+        thread::spawn(move || {
+            for line in BufReader::new(stream).lines().map_while(Result::ok) {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let (severity, _) = parser::log_severity(&line);
+                let _ = app.emit("setup-log", DownloadLogPayload { line, severity });
+            }
+        })
+    };
+    let readers = [
+        child.stdout.take().map(|out| emit_lines(app.clone(), Box::new(out))),
+        child.stderr.take().map(|err| emit_lines(app.clone(), Box::new(err))),
+    ];
+
+    let status = child.wait().map_err(|e| format!("Failed to wait for {}: {}", program, e))?;
+    for reader in readers.into_iter().flatten() {
+        let _ = reader.join();
+    }
+    if !status.success() {
+        return Err(format!("{} failed ({}), see the setup log for details", program, status));
+    }
+    Ok(())
+}
+
+#[derive(Clone, Serialize)]
+struct BenchResult {
+    threads: u32,
+    seconds: f64,
+    songs: u32,
+    songs_per_min: f64,
+    recommended: bool,
+}
+
+#[derive(Clone, Serialize)]
+struct BenchmarkProgressPayload {
+    threads: u32,
+    run: u32,
+    total_runs: u32,
+}
+
+/// Download a sample at each thread count into a temp folder and compare the speeds
+#[tauri::command]
+async fn benchmark_threads(
+    app: AppHandle,
+    sample_url: String,
+    thread_counts: Vec<u32>,
+    state: tauri::State<'_, DownloadState>,
+) -> Result<Vec<BenchResult>, String> {
+    if thread_counts.is_empty() {
+        return Err("Provide at least one thread count to benchmark".to_string());
+    }
+    if let Some(bad) = thread_counts.iter().find(|t| !(1..=32).contains(*t)) {
+        return Err(format!("Invalid thread count {}, use a value between 1 and 32", bad));
+    }
+    let _busy = BusyGuard::acquire(&state)
+        .map_err(|_| "Wait for the current download to finish before benchmarking".to_string())?;
+
+    state.is_cancelled.store(false, Ordering::SeqCst);
+    let spotdl_path = spotdl_path(&app)?;
+    let mut results = Vec::new();
+
+    for (run, &threads) in thread_counts.iter().enumerate() {
+        let _ = app.emit("benchmark-progress", BenchmarkProgressPayload {
+            threads,
+            run: run as u32 + 1,
+            total_runs: thread_counts.len() as u32,
+        });
+
+        // Each run starts from an empty folder so nothing is skipped as already downloaded
+        let bench_dir = std::env::temp_dir().join(format!("spotify-downloader-bench-{}", threads));
+        let _ = fs::remove_dir_all(&bench_dir);
+        fs::create_dir_all(&bench_dir).map_err(|e| format!("Failed to create benchmark folder: {}", e))?;
+
+        let started = Instant::now();
+        let mut cmd = spotdl_command(&app, &spotdl_path);
+        cmd.arg("--threads").arg(threads.to_string())
+            .args(ffmpeg_args(&app))
+            .arg(&sample_url)
+            .current_dir(&bench_dir)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        isolate_process_group(&mut cmd);
+        let child = cmd.spawn();
+        let mut child = match child {
+            Ok(child) => child,
+            Err(e) => {
+                let _ = fs::remove_dir_all(&bench_dir);
+                return Err(format!("Failed to run spotdl: {}", e));
+            }
+        };
+
+        state.child_pid.store(child.id(), Ordering::SeqCst);
+        let status = wait_for_exit(&mut child, &state);
+        state.child_pid.store(0, Ordering::SeqCst);
+        state.pause_reasons.store(0, Ordering::SeqCst);
+
+        let seconds = started.elapsed().as_secs_f64();
+        let songs = recent_audio_files(&bench_dir, SystemTime::UNIX_EPOCH).len() as u32;
+        let _ = fs::remove_dir_all(&bench_dir);
+
+        if state.is_cancelled.load(Ordering::SeqCst) {
+            return Err("Benchmark cancelled by user".to_string());
+        }
+        match status {
+            Ok(status) if status.success() && songs > 0 => {}
+            _ => return Err(format!("Benchmark run with {} threads failed", threads)),
+        }
+
+        let songs_per_min = if seconds > 0.0 { songs as f64 / seconds * 60.0 } else { 0.0 };
+        results.push(BenchResult { threads, seconds, songs, songs_per_min, recommended: false });
+    }
+
+    // Recommend the fastest setting
+    if let Some(best) = results.iter_mut().max_by(|a, b| a.songs_per_min.total_cmp(&b.songs_per_min)) {
+        best.recommended = true;
+    }
+    Ok(results)
+}
+
+// Public track the self-test downloads and the credential check resolves,
+// a few minutes long and available everywhere
+const TEST_TRACK_URL: &str = "https://open.spotify.com/track/4uLU6hMCjMI75M1A2tKUQC";
+const SELF_TEST_TIMEOUT_SECS: u32 = 180;
+
+#[derive(Clone, Serialize)]
+struct SelfTestStage {
+    name: &'static str,
+    passed: bool,
+    millis: u64,
+    detail: Option<String>,  // what was found, or why the stage failed
+}
+
+#[derive(Clone, Serialize)]
+struct SelfTestReport {
+    passed: bool,
+    stages: Vec<SelfTestStage>,
+}
+
+impl SelfTestReport {
+    fn stage(&mut self, name: &'static str, started: Instant, result: Result<String, String>) -> bool {
+        let passed = result.is_ok();
+        self.stages.push(SelfTestStage {
+            name,
+            passed,
+            millis: started.elapsed().as_millis() as u64,
+            detail: Some(result.unwrap_or_else(|e| e)),
+        });
+        self.passed &= passed;
+        passed
+    }
+}
+
+/// Check the whole setup by downloading one known track into a temp folder: find spotdl,
+/// download, look for the file and clean up. Stages after a failed one are not run
+#[tauri::command]
+async fn run_self_test(
+    app: AppHandle,
+    timeout_secs: Option<u32>,
+    state: tauri::State<'_, DownloadState>,
+) -> Result<SelfTestReport, String> {
+    let _busy = BusyGuard::acquire(&state)
+        .map_err(|_| "Wait for the current download to finish before running the self-test".to_string())?;
+    state.is_cancelled.store(false, Ordering::SeqCst);
+    let timeout = Duration::from_secs(timeout_secs.unwrap_or(SELF_TEST_TIMEOUT_SECS).max(1).into());
+    let mut report = SelfTestReport { passed: true, stages: Vec::new() };
+
+    let started = Instant::now();
+    let spotdl_path = match spotdl_path(&app) {
+        Ok(path) => {
+            let version = command_version(spotdl_command(&app, &path).arg("--version"));
+            let found = format!("{} ({})", path.display(), version.as_deref().unwrap_or("unknown version"));
+            report.stage("resolve_spotdl", started, Ok(found));
+            path
+        }
+        Err(e) => {
+            report.stage("resolve_spotdl", started, Err(e));
+            return Ok(report);
+        }
+    };
+
+    let test_dir = std::env::temp_dir().join("spotify-downloader-self-test");
+    let _ = fs::remove_dir_all(&test_dir);
+    fs::create_dir_all(&test_dir).map_err(|e| format!("Failed to create self-test folder: {}", e))?;
+
+    let started = Instant::now();
+    let mut cmd = spotdl_command(&app, &spotdl_path);
+    cmd.args(ffmpeg_args(&app))
+        .args(credential_args(&app))
+        .args(proxy_args(&app))
+        .arg(TEST_TRACK_URL)
+        .current_dir(&test_dir)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    isolate_process_group(&mut cmd);
+    let downloaded = spawn_tracked(&mut cmd, &state).and_then(|mut child| {
+        let status = wait_with_timeout(&mut child, &state, timeout);
+        state.child_pid.store(0, Ordering::SeqCst);
+        state.pause_reasons.store(0, Ordering::SeqCst);
+        match status? {
+            status if status.success() => Ok(format!("spotdl finished in {:.1}s", started.elapsed().as_secs_f64())),
+            status => Err(format!("spotdl exited with {}", status)),
+        }
+    });
+
+    if report.stage("download", started, downloaded) {
+        let started = Instant::now();
+        let files = recent_audio_files(&test_dir, SystemTime::UNIX_EPOCH);
+        let found = match files.first() {
+            Some(file) => Ok(format!("{} ({} KB)", file.display(), fs::metadata(file).map_or(0, |m| m.len() / 1024))),
+            None => Err("spotdl finished but no audio file was written".to_string()),
+        };
+        report.stage("verify_output", started, found);
+    }
+
+    let started = Instant::now();
+    let cleaned = fs::remove_dir_all(&test_dir)
+        .map(|_| format!("Removed {}", test_dir.display()))
+        .map_err(|e| format!("Failed to remove {}: {}", test_dir.display(), e));
+    report.stage("cleanup", started, cleaned);
+    Ok(report)
+}
+
+/// wait_for_exit, killing the process once `timeout` has passed
+fn wait_with_timeout(child: &mut Child, state: &DownloadState, timeout: Duration) -> Result<ExitStatus, String> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Some(status) = child.try_wait().map_err(|e| format!("Failed to wait for spotdl: {}", e))? {
+            return Ok(status);
+        }
+        if state.is_cancelled.load(Ordering::SeqCst) {
+            let _ = wait_for_exit(child, state);
+            return Err("Self-test cancelled by user".to_string());
+        }
+        if Instant::now() >= deadline {
+            kill_process(child.id());
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(format!("spotdl did not finish within {} seconds", timeout.as_secs()));
+        }
+        thread::sleep(EXIT_POLL_INTERVAL);
+    }
+}
+
+#[derive(Clone, Serialize)]
+struct ReconcileReport {
+    to_add: Vec<String>,
+    orphans: Vec<String>,
+    downloaded: u32,
+}
+
+/// Client id and secret from the settings, when both are configured
+fn spotify_credentials(app: &AppHandle) -> Option<(String, String)> {
+    let settings = app.state::<Mutex<Settings>>();
+    let settings = settings.lock().ok()?;
+    let id = settings.client_id.as_deref().map(str::trim).filter(|id| !id.is_empty())?;
+    let secret = settings.client_secret.as_deref().map(str::trim).filter(|secret| !secret.is_empty())?;
+    Some((id.to_string(), secret.to_string()))
+}
+
+fn credential_args(app: &AppHandle) -> Vec<String> {
+    spotify_credentials(app)
+        .map(|(id, secret)| vec!["--client-id".to_string(), id, "--client-secret".to_string(), secret])
+        .unwrap_or_default()
+}
+
+/// A bare spotdl command whose PATH starts with the folders from the settings, so
+/// python and ffmpeg are found even when the app was started with a minimal PATH
+fn spotdl_command(app: &AppHandle, spotdl_path: &Path) -> Command {
+    let mut cmd = Command::new(spotdl_path);
+    if let Some(path) = search_path(app) {
+        cmd.env("PATH", path);
+    }
+    cmd
+}
+
+fn search_path(app: &AppHandle) -> Option<std::ffi::OsString> {
+    let extra: Vec<PathBuf> = app.state::<Mutex<Settings>>()
+        .lock()
+        .ok()?
+        .extra_path
+        .iter()
+        .map(|dir| PathBuf::from(dir.trim()))
+        .collect();
+    if extra.is_empty() {
+        return None;
+    }
+
+    let inherited = std::env::var_os("PATH").unwrap_or_default();
+    std::env::join_paths(extra.into_iter().chain(std::env::split_paths(&inherited))).ok()
+}
+
+fn ffmpeg_setting(app: &AppHandle) -> Option<String> {
+    let settings = app.state::<Mutex<Settings>>();
+    let settings = settings.lock().ok()?;
+    settings.ffmpeg_path.as_deref().map(str::trim).filter(|path| !path.is_empty()).map(str::to_string)
+}
+
+/// The configured ffmpeg, or the one on the search path
+fn ffmpeg_command(app: &AppHandle) -> Command {
+    let mut ffmpeg = Command::new(ffmpeg_setting(app).unwrap_or_else(|| "ffmpeg".to_string()));
+    if let Some(path) = search_path(app) {
+        ffmpeg.env("PATH", path);
+    }
+    ffmpeg
+}
+
+fn ffmpeg_args(app: &AppHandle) -> Vec<String> {
+    ffmpeg_setting(app)
+        .map(|path| vec!["--ffmpeg".to_string(), path])
+        .unwrap_or_default()
+}
+
+/// The configured proxy URL, if any
+fn proxy_setting(app: &AppHandle) -> Option<String> {
+    let settings = app.state::<Mutex<Settings>>();
+    let settings = settings.lock().ok()?;
+    settings.proxy.as_deref().map(str::trim).filter(|proxy| !proxy.is_empty()).map(str::to_string)
+}
+
+fn proxy_args(app: &AppHandle) -> Vec<String> {
+    proxy_setting(app)
+        .map(|proxy| vec!["--proxy".to_string(), proxy])
+        .unwrap_or_default()
+}
+
+/// The user:password part of a proxy URL, if it has one
+fn proxy_credentials(proxy: &str) -> Option<&str> {
+    let rest = proxy.split_once("://").map_or(proxy, |(_, rest)| rest);
+    rest.rsplit_once('@').map(|(credentials, _)| credentials).filter(|c| !c.is_empty())
+}
+
+// run_spotdl_debug only runs these: subcommands that look songs up without downloading them
+const DEBUG_SUBCOMMANDS: &[&str] = &["url", "save"];
+const DEBUG_LOG_LEVELS: &[&str] = &["DEBUG", "INFO", "WARNING", "ERROR"];
+const DEBUG_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Check run_spotdl_debug's arguments against its allowlist: `--version`, `--help`, or a
+/// debug subcommand with Spotify links, `--help`, `--log-level` and `--audio`. Anything
+/// else is refused. Links come back in their canonical form
+fn debug_args(args: &[String]) -> Result<Vec<String>, String> {
+    let args: Vec<&str> = args.iter().map(|arg| arg.trim()).filter(|arg| !arg.is_empty()).collect();
+    match args.as_slice() {
+        [] => return Err("Enter a spotdl command to run".to_string()),
+        ["--version" | "--help"] => return Ok(vec![args[0].to_string()]),
+        [subcommand, ..] if !DEBUG_SUBCOMMANDS.contains(subcommand) => {
+            return Err(format!(
+                "'{}' can't be run from here, use --version, --help or one of: {}",
+                subcommand,
+                DEBUG_SUBCOMMANDS.join(", ")
+            ));
+        }
+        _ => {}
+    }
+
+    let mut checked = vec![args[0].to_string()];
+    let mut rest = args[1..].iter().peekable();
+    let mut has_link = false;
+    while let Some(&arg) = rest.next() {
+        match arg {
+            "--help" => {
+                checked.push(arg.to_string());
+                has_link = true;  // help needs no link
+            }
+            "--log-level" => {
+                let level = rest.next().map(|level| level.to_uppercase()).filter(|level| DEBUG_LOG_LEVELS.contains(&level.as_str()));
+                let level = level.ok_or_else(|| format!("--log-level takes one of: {}", DEBUG_LOG_LEVELS.join(", ")))?;
+                checked.extend([arg.to_string(), level]);
+            }
+            "--audio" => {
+                checked.push(arg.to_string());
+                let mut providers = 0;
+                while let Some(&&provider) = rest.peek().filter(|provider| AUDIO_PROVIDERS.contains(provider)) {
+                    checked.push(provider.to_string());
+                    rest.next();
+                    providers += 1;
+                }
+                if providers == 0 {
+                    return Err(format!("--audio takes one or more of: {}", AUDIO_PROVIDERS.join(", ")));
+                }
+            }
+            flag if flag.starts_with('-') => return Err(format!("The flag '{}' is not allowed here", flag)),
+            link => {
+                checked.push(normalize_spotify_url(link)?);
+                has_link = true;
+            }
+        }
+    }
+    if !has_link {
+        return Err(format!("spotdl {} needs a Spotify link", args[0]));
+    }
+    Ok(checked)
+}
+
+/// Run an allowlisted spotdl command for troubleshooting and return its combined output.
+/// A narrow escape hatch for diagnostics, see debug_args for what it accepts
+#[tauri::command]
+async fn run_spotdl_debug(app: AppHandle, args: Vec<String>) -> Result<String, String> {
+    let args = debug_args(&args)?;
+    let spotdl_path = spotdl_path(&app)?;
+    let stamp = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).map(|d| d.as_millis()).unwrap_or(0);
+    let output_file = std::env::temp_dir().join(format!("spotify-downloader-debug-{}.log", stamp));
+    let save_file = std::env::temp_dir().join(format!("spotify-downloader-debug-{}.spotdl", stamp));
+
+    // Both streams go to one file, so they stay in order and a chatty run can't fill a pipe
+    let output = fs::File::create(&output_file).map_err(|e| format!("Failed to create debug output file: {}", e))?;
+    let errors = output.try_clone().map_err(|e| format!("Failed to create debug output file: {}", e))?;
+    let mut cmd = spotdl_command(&app, &spotdl_path);
+    cmd.args(&args);
+    if DEBUG_SUBCOMMANDS.contains(&args[0].as_str()) {
+        cmd.args(credential_args(&app)).args(proxy_args(&app));
+    }
+    if args[0] == "save" {
+        cmd.arg("--save-file").arg(&save_file);
+    }
+    cmd.stdin(Stdio::null()).stdout(output).stderr(errors);
+    force_line_output(&mut cmd);
+    isolate_process_group(&mut cmd);
+
+    let mut child = cmd.spawn().map_err(|e| format!("Failed to run spotdl: {}", e))?;
+    let deadline = Instant::now() + DEBUG_TIMEOUT;
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break Some(status),
+            Ok(None) if Instant::now() < deadline => thread::sleep(Duration::from_millis(100)),
+            _ => {
+                kill_process(child.id());
+                let _ = child.kill();
+                let _ = child.wait();
+                break None;
+            }
+        }
+    };
+
+    let text = fs::read_to_string(&output_file).unwrap_or_default();
+    let _ = fs::remove_file(&output_file);
+    let _ = fs::remove_file(&save_file);
+    let secrets: Vec<String> = spotify_credentials(&app)
+        .into_iter()
+        .flat_map(|(id, secret)| [secret, id])
+        .chain(proxy_setting(&app).as_deref().and_then(proxy_credentials).map(str::to_string))
+        .collect();
+    let text = secrets.iter().fold(text, |text, secret| text.replace(secret.as_str(), "***"));
+
+    let outcome = match status {
+        Some(status) => format!("[{}]", status),
+        None => format!("[killed after {} seconds]", DEBUG_TIMEOUT.as_secs()),
+    };
+    Ok(format!("$ spotdl {}\n{}\n{}", args.join(" "), text.trim_end(), outcome))
+}
+
+/// Check a Spotify client id/secret with a one-track metadata fetch
+#[tauri::command]
+async fn test_spotify_credentials(app: AppHandle, client_id: String, client_secret: String) -> Result<(), String> {
+    let (client_id, client_secret) = (client_id.trim().to_string(), client_secret.trim().to_string());
+    if client_id.is_empty() || client_secret.is_empty() {
+        return Err("Enter both a client id and a client secret".to_string());
+    }
+
+    let spotdl_path = spotdl_path(&app)?;
+    let mut args = vec!["--client-id".to_string(), client_id, "--client-secret".to_string(), client_secret];
+    args.extend(proxy_args(&app));
+    // spotdl's own error could quote the credentials, so it is not passed on
+    metadata::fetch_songs(spotdl_command(&app, &spotdl_path), TEST_TRACK_URL, &args)
+        .map(|_| ())
+        .map_err(|_| "Spotify did not accept these credentials".to_string())
+}
+
+/// List the songs behind a URL without downloading any audio
+#[tauri::command]
+async fn preview_tracks(app: AppHandle, url: String) -> Result<Vec<TrackInfo>, String> {
+    validate_spotify_url(&url)?;
+    let spotdl_path = spotdl_path(&app)?;
+    let songs = metadata::fetch_songs(spotdl_command(&app, &spotdl_path), &url, &[credential_args(&app), proxy_args(&app)].concat())?;
+    Ok(songs.into_iter().map(TrackInfo::from).collect())
+}
+
+#[derive(Clone, Serialize)]
+struct SizeEstimate {
+    bytes: u64,
+    human: String,  // e.g. "about 84.2 MB"
+}
+
+/// Approximate download size of a URL's songs in `format`, from their lengths and the
+/// bitrate. Real files differ, lossless formats especially, so treat it as a ballpark
+#[tauri::command]
+async fn estimate_download_size(app: AppHandle, url: String, format: String, bitrate: Option<String>) -> Result<SizeEstimate, String> {
+    validate_spotify_url(&url)?;
+    let format = format.trim().to_lowercase();
+    if !AUDIO_FORMATS.contains(&format.as_str()) {
+        return Err(format!(
+            "Unsupported audio format '{}'. Supported formats: {}",
+            format,
+            AUDIO_FORMATS.join(", ")
+        ));
+    }
+    let bitrate = bitrate.filter(|b| !b.trim().is_empty()).or_else(|| default_bitrate_for_format(&format).map(str::to_string));
+
+    let spotdl_path = spotdl_path(&app)?;
+    let songs = metadata::fetch_songs(spotdl_command(&app, &spotdl_path), &url, &[credential_args(&app), proxy_args(&app)].concat())?;
+    let bytes = songs
+        .iter()
+        .map(|song| match song.duration {
+            0 => disk_space::estimated_track_bytes(&format, bitrate.as_deref()),
+            secs => disk_space::estimated_bytes(&format, bitrate.as_deref(), secs.into()),
+        })
+        .sum();
+    Ok(SizeEstimate { bytes, human: format!("about {}", disk_space::format_size(bytes)) })
+}
+
+/// Compare a playlist with a local folder: songs missing locally and local files no
+/// longer in the playlist. Optionally downloads the missing songs; orphans are only listed.
+#[tauri::command]
+async fn reconcile(
+    app: AppHandle,
+    url: String,
+    path: String,
+    download_missing: bool,
+    state: tauri::State<'_, DownloadState>,
+) -> Result<ReconcileReport, String> {
+    let dir = Path::new(&path);
+    if !dir.is_dir() {
+        return Err(format!("Folder does not exist: {}", path));
+    }
+    // Held through the lookup too, so a download can't start before the missing songs do
+    let _busy = BusyGuard::acquire(&state)?;
+    state.is_cancelled.store(false, Ordering::SeqCst);
+
+    let spotdl_path = spotdl_path(&app)?;
+    let songs = fetch_songs_tracked(&app, &state, &spotdl_path, &url);
+    if state.is_cancelled.load(Ordering::SeqCst) {
+        return Err("Reconcile cancelled by user".to_string());
+    }
+    let songs = songs?;
+    let local_files = recent_audio_files(dir, SystemTime::UNIX_EPOCH);
+    let local_names: Vec<(String, &PathBuf)> = local_files
+        .iter()
+        .filter_map(|file| Some((metadata::normalize_name(&file.file_stem()?.to_string_lossy()), file)))
+        .collect();
+
+    let missing: Vec<&metadata::SongMetadata> = songs
+        .iter()
+        .filter(|song| {
+            let name = metadata::normalize_name(&song.display_name());
+            !local_names.iter().any(|(local, _)| *local == name)
+        })
+        .collect();
+    let playlist_names: Vec<String> = songs.iter().map(|song| metadata::normalize_name(&song.display_name())).collect();
+    let orphans = local_names
+        .iter()
+        .filter(|(local, _)| !playlist_names.contains(local))
+        .map(|(_, file)| file.to_string_lossy().into_owned())
+        .collect();
+
+    let mut downloaded = 0;
+    if download_missing && !missing.is_empty() {
+        let started_at = SystemTime::now();
+        let mut cmd = spotdl_command(&app, &spotdl_path);
+        cmd.args(ffmpeg_args(&app))
+            .args(credential_args(&app))
+            .args(proxy_args(&app))
+            .args(missing.iter().map(|song| song.url.as_str()))
+            .current_dir(dir)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        isolate_process_group(&mut cmd);
+        let mut child = spawn_tracked(&mut cmd, &state)?;
+        let status = wait_for_exit(&mut child, &state);
+        state.child_pid.store(0, Ordering::SeqCst);
+        state.pause_reasons.store(0, Ordering::SeqCst);
+
+        if state.is_cancelled.load(Ordering::SeqCst) {
+            return Err("Reconcile cancelled by user".to_string());
+        }
+        status?;
+        downloaded = recent_audio_files(dir, started_at).len() as u32;
+    }
+
+    Ok(ReconcileReport {
+        to_add: missing.iter().map(|song| song.display_name()).collect(),
+        orphans,
+        downloaded,
+    })
+}
+
+#[derive(Clone, Serialize)]
+struct SyncReport {
+    added: u32,
+    removed: u32,
+    updated: u32,
+    unchanged: u32,
+    failed: u32,
+    save_file: String,
+}
+
+/// Mirror a playlist or album into `path` with `spotdl sync`: new songs are downloaded and
+/// ones that left it are deleted. The sync state is kept in a .spotdl file next to the songs,
+/// so each sync compares against the previous one
+#[tauri::command]
+async fn sync_playlist(
+    app: AppHandle,
+    url: String,
+    path: String,
+    state: tauri::State<'_, DownloadState>,
+) -> Result<SyncReport, String> {
+    let (kind, id) = spotify_url::parse(&url)?;
+    if !matches!(kind, ContentKind::Playlist | ContentKind::Album) {
+        return Err("Only playlists and albums can be synced to a folder".to_string());
+    }
+    let _busy = BusyGuard::acquire(&state)?;
+    state.is_cancelled.store(false, Ordering::SeqCst);
+
+    let dir = Path::new(&path);
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create directory: {}", e))?;
+    // Named after the id alone, so a URI, a localized link and a share link all find the same file
+    let save_file = dir.join(format!("{}.spotdl", id));
+
+    let settings = app.state::<Mutex<Settings>>().lock().map(|s| s.clone()).unwrap_or_default();
+    let spotdl_path = spotdl_path(&app)?;
+    let mut cmd = spotdl_command(&app, &spotdl_path);
+    cmd.arg("sync")
+        .arg("--log-level").arg("INFO")
+        .arg("--log-format").arg(parser::LOG_FORMAT)
+        .args(ffmpeg_args(&app))
+        .args(credential_args(&app))
+        .args(proxy_args(&app))
+        .arg("--format").arg(&settings.format)
+        .arg("--threads").arg(settings.threads.to_string());
+    if let Some(bitrate) = settings.bitrate.as_deref().or(default_bitrate_for_format(&settings.format)) {
+        cmd.arg("--bitrate").arg(bitrate);
+    }
+    // The first sync starts from the link, later ones from the state it saved
+    if save_file.exists() {
+        cmd.arg(&save_file);
+    } else {
+        cmd.arg(&url).arg("--save-file").arg(&save_file);
+    }
+    cmd.current_dir(dir);
+    force_line_output(&mut cmd);
+    isolate_process_group(&mut cmd);
+    cmd.stdout(Stdio::piped());
+    cmd.stderr(Stdio::piped());
+
+    let session = Arc::new(DownloadSession {
+        secrets: spotify_credentials(&app)
+            .into_iter()
+            .flat_map(|(id, secret)| [secret, id])
+            .chain(settings.proxy.as_deref().and_then(proxy_credentials).map(str::to_string))
+            .collect(),
+        ..DownloadSession::new(false, &settings.format)
+    });
+    emit_progress(&app, ProgressPayload {
+        permille: START_PERMILLE,
+        percent: START_PERMILLE / 10,
+        message: "Syncing folder with the playlist...".to_string(),
+        current_track: 0,
+        total_tracks: 0,
+        speed: "".to_string(),
+        eta_seconds: None,
+        bytes_per_sec: None,
+        current_title: None,
+    });
+
+    let status = run_spotdl(&app, &state, cmd, &session);
+    state.child_pid.store(0, Ordering::SeqCst);
+    state.pause_reasons.store(0, Ordering::SeqCst);
+    if state.is_cancelled.load(Ordering::SeqCst) {
+        download_cancelled(&app);
+        return Err("Sync cancelled by user".to_string());
+    }
+
+    if !status?.success() {
+        let errors = session.recent_errors.lock().map(|e| Vec::from(e.clone())).unwrap_or_default();
+        if errors.is_empty() {
+            return Err("Sync failed. Please check the URL and try again.".to_string());
+        }
+        return Err(format!("Sync failed: {}", errors.join(" | ")));
+    }
+
+    let counts = session.counts();
+    let updated = session.updated.load(Ordering::SeqCst);
+    Ok(SyncReport {
+        added: counts.downloaded,
+        removed: session.removed.load(Ordering::SeqCst),
+        updated,
+        unchanged: counts.skipped.saturating_sub(updated),
+        failed: counts.failed,
+        save_file: save_file.to_string_lossy().into_owned(),
+    })
+}
+
+#[derive(Clone, Serialize)]
+struct RetagReport {
+    retagged: u32,
+    missing: u32,  // songs with no file in the folder, left alone
+    failed: u32,
+}
+
+/// Refresh the tags of songs already in `path` from Spotify without downloading their audio
+/// again. Only songs whose file is found in the folder are handed to spotdl, with
+/// `--overwrite metadata` and the format of that file
+#[tauri::command]
+async fn retag(
+    app: AppHandle,
+    url: String,
+    path: String,
+    state: tauri::State<'_, DownloadState>,
+) -> Result<RetagReport, String> {
+    validate_spotify_url(&url)?;
+    let dir = Path::new(&path);
+    if !dir.is_dir() {
+        return Err(format!("Folder does not exist: {}", path));
+    }
+    let _busy = BusyGuard::acquire(&state)?;
+    state.is_cancelled.store(false, Ordering::SeqCst);
+
+    emit_progress(&app, ProgressPayload {
+        permille: START_PERMILLE,
+        percent: START_PERMILLE / 10,
+        message: "Looking up the songs to re-tag...".to_string(),
+        current_track: 0,
+        total_tracks: 0,
+        speed: "".to_string(),
+        eta_seconds: None,
+        bytes_per_sec: None,
+        current_title: None,
+    });
+    let spotdl_path = spotdl_path(&app)?;
+    let songs = fetch_download_songs(&app, &state, &spotdl_path, &url)?;
+
+    // spotdl only finds a song's file under its default name, so only the folder itself is searched
+    let files: HashMap<String, String> = recent_audio_files(dir, SystemTime::UNIX_EPOCH)
+        .into_iter()
+        .filter(|file| file.parent() == Some(dir))
+        .filter_map(|file| {
+            let stem = file.file_stem()?.to_string_lossy();
+            let extension = file.extension()?.to_string_lossy().to_lowercase();
+            Some((metadata::normalize_name(&stem), extension))
+        })
+        .collect();
+    let mut by_format: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+    for song in &songs {
+        if let Some(format) = files.get(&metadata::normalize_name(&song.display_name())) {
+            by_format.entry(format.as_str()).or_default().push(song.url.clone());
+        }
+    }
+    let found = by_format.values().map(Vec::len).sum::<usize>() as u32;
+    if found == 0 {
+        return Err(format!("None of the songs are in {}, download them first", path));
+    }
+
+    let settings = app.state::<Mutex<Settings>>().lock().map(|s| s.clone()).unwrap_or_default();
+    let session = Arc::new(DownloadSession {
+        secrets: spotify_credentials(&app)
+            .into_iter()
+            .flat_map(|(id, secret)| [secret, id])
+            .chain(settings.proxy.as_deref().and_then(proxy_credentials).map(str::to_string))
+            .collect(),
+        fixed_total: true,
+        total_tracks: Mutex::new(found),
+        ..DownloadSession::new(false, &settings.format)
+    });
+    emit_progress(&app, ProgressPayload {
+        permille: START_PERMILLE,
+        percent: START_PERMILLE / 10,
+        message: format!("Re-tagging {} song(s)...", found),
+        current_track: 0,
+        total_tracks: found,
+        speed: "".to_string(),
+        eta_seconds: None,
+        bytes_per_sec: None,
+        current_title: None,
+    });
+
+    // One run per format, spotdl looks for files with the extension of --format
+    let mut succeeded = true;
+    for (format, urls) in &by_format {
+        let mut cmd = spotdl_command(&app, &spotdl_path);
+        cmd.arg("download")
+            .args(urls)
+            .arg("--overwrite").arg("metadata")
+            .arg("--format").arg(format)
+            .arg("--log-level").arg("INFO")
+            .arg("--log-format").arg(parser::LOG_FORMAT)
+            .args(ffmpeg_args(&app))
+            .args(credential_args(&app))
+            .args(proxy_args(&app))
+            .arg("--threads").arg(settings.threads.to_string())
+            .current_dir(dir);
+        force_line_output(&mut cmd);
+        isolate_process_group(&mut cmd);
+        cmd.stdout(Stdio::piped());
+        cmd.stderr(Stdio::piped());
+
+        let status = run_spotdl(&app, &state, cmd, &session);
+        state.child_pid.store(0, Ordering::SeqCst);
+        state.pause_reasons.store(0, Ordering::SeqCst);
+        if state.is_cancelled.load(Ordering::SeqCst) {
+            download_cancelled(&app);
+            return Err("Re-tagging cancelled by user".to_string());
+        }
+        succeeded &= status?.success();
+    }
+
+    let counts = session.counts();
+    if !succeeded && counts.failed == 0 {
+        let errors = session.recent_errors.lock().map(|e| Vec::from(e.clone())).unwrap_or_default();
+        if errors.is_empty() {
+            return Err("Re-tagging failed. Please check the URL and try again.".to_string());
+        }
+        return Err(format!("Re-tagging failed: {}", errors.join(" | ")));
+    }
+    Ok(RetagReport {
+        retagged: session.updated.load(Ordering::SeqCst),
+        missing: songs.len() as u32 - found,
+        failed: counts.failed,
+    })
+}
+
+/// Download one track to exactly `output_file`, returns the path it was saved to. The file's
+/// extension picks the format, and an existing file is never overwritten
+#[tauri::command]
+async fn download_single(
+    app: AppHandle,
+    url: String,
+    output_file: String,
+    state: tauri::State<'_, DownloadState>,
+) -> Result<String, String> {
+    if validate_spotify_url(&url)? != ContentKind::Track {
+        return Err("Only a single track can be saved to a chosen file, this link is a collection".to_string());
+    }
+
+    let file = PathBuf::from(output_file.trim());
+    if !file.is_absolute() {
+        return Err(format!("Output file must be an absolute path: {}", output_file));
+    }
+    if file.exists() {
+        return Err(format!("File already exists: {}", file.display()));
+    }
+    let (Some(dir), Some(stem)) = (file.parent(), file.file_stem().and_then(|stem| stem.to_str())) else {
+        return Err(format!("Invalid output file: {}", output_file));
+    };
+    // spotdl would read braces as template keys
+    if stem.contains(['{', '}']) {
+        return Err("The file name can't contain { or }".to_string());
+    }
+    let format = file.extension().map(|ext| ext.to_string_lossy().to_lowercase()).unwrap_or_default();
+    if !AUDIO_FORMATS.contains(&format.as_str()) {
+        return Err(format!(
+            "Unsupported audio format '{}'. Supported formats: {}",
+            format,
+            AUDIO_FORMATS.join(", ")
+        ));
+    }
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create directory: {}", e))?;
+
+    let _busy = BusyGuard::acquire(&state)?;
+    state.is_cancelled.store(false, Ordering::SeqCst);
+
+    let settings = app.state::<Mutex<Settings>>().lock().map(|s| s.clone()).unwrap_or_default();
+    let spotdl_path = spotdl_path(&app)?;
+    let mut cmd = spotdl_command(&app, &spotdl_path);
+    cmd.arg("download")
+        .arg(&url)
+        .arg("--output").arg(dir.join(format!("{}.{{output-ext}}", stem)))
+        .arg("--format").arg(&format)
+        .arg("--log-level").arg("INFO")
+        .arg("--log-format").arg(parser::LOG_FORMAT)
+        .args(ffmpeg_args(&app))
+        .args(credential_args(&app))
+        .args(proxy_args(&app))
+        .current_dir(dir);
+    if let Some(bitrate) = settings.bitrate.as_deref().or(default_bitrate_for_format(&format)) {
+        cmd.arg("--bitrate").arg(bitrate);
+    }
+    force_line_output(&mut cmd);
+    isolate_process_group(&mut cmd);
+    cmd.stdout(Stdio::piped());
+    cmd.stderr(Stdio::piped());
+
+    let session = Arc::new(DownloadSession {
+        secrets: spotify_credentials(&app)
+            .into_iter()
+            .flat_map(|(id, secret)| [secret, id])
+            .chain(settings.proxy.as_deref().and_then(proxy_credentials).map(str::to_string))
+            .collect(),
+        fixed_total: true,
+        ..DownloadSession::new(false, &format)
+    });
+    emit_progress(&app, ProgressPayload {
+        permille: START_PERMILLE,
+        percent: START_PERMILLE / 10,
+        message: "Downloading track...".to_string(),
+        current_track: 0,
+        total_tracks: 1,
+        speed: "".to_string(),
+        eta_seconds: None,
+        bytes_per_sec: None,
+        current_title: None,
+    });
+
+    let status = run_spotdl(&app, &state, cmd, &session);
+    state.child_pid.store(0, Ordering::SeqCst);
+    state.pause_reasons.store(0, Ordering::SeqCst);
+    if state.is_cancelled.load(Ordering::SeqCst) {
+        download_cancelled(&app);
+        return Err("Download cancelled by user".to_string());
+    }
+
+    // spotdl exits cleanly when it couldn't find the song, so the file has the final say
+    if !status?.success() || !file.exists() {
+        let errors = session.recent_errors.lock().map(|e| Vec::from(e.clone())).unwrap_or_default();
+        if errors.is_empty() {
+            return Err(format!("Download failed, {} was not written", file.display()));
+        }
+        return Err(format!("Download failed: {}", errors.join(" | ")));
+    }
+    Ok(file.to_string_lossy().into_owned())
+}
+
+/// Everything about a download besides its URL and content type. The frontend sends it as
+/// one object, so new options don't change the commands and old callers get the defaults
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+struct DownloadOptions {
+    threads: u32,
+    download_path: String,
+    format: String,
+    bitrate: Option<String>,
+    embed_plain_lyrics: bool,
+    generate_synced_lrc: bool,
+    lyrics_providers: Vec<String>,
+    audio_providers: Vec<String>,
+    save_metadata_file: Option<String>,
+    collection: Option<String>,
+    group_by_album: bool,
+    fail_fast: bool,
+    output_template: Option<String>,
+    generate_m3u: bool,
+    overwrite: String,
+    extra_spotdl_args: Vec<String>,  // flags we don't model, passed on verbatim
+    expected_tracks: Option<u32>,  // from a preview, for the disk space check
+    cookie_file: Option<String>,  // Netscape cookies.txt for age-gated or members-only videos
+    per_track: bool,  // one spotdl run per song, so a stuck song can be skipped
+    timeout_secs: Option<u32>,  // the whole download is killed after this long
+    create_playlist_subfolder: bool,  // save into a folder named after the playlist or album
+    embed_album_art: bool,
+    save_cover_file: bool,  // a cover.jpg in each folder, for Plex and Jellyfin
+    restrict_filenames: bool,  // ASCII-only file names, for FAT SD cards and old players
+    // No album art or lyrics lookup, for users who add those in their own tool. spotdl
+    // still writes the text tags, there is no flag to leave them out
+    #[serde(alias = "skip_metadata")]
+    skip_art_and_lyrics: bool,
+    conversion_threads: Option<u32>,  // caps the threads for formats ffmpeg has to re-encode
+    max_retries: u32,  // per-track mode only, tries again after a network error or rate limiting
+    delay_between_tracks_ms: Option<u32>,  // per-track mode only, a pause between songs to stay under rate limits
+    ramp_up: bool,  // start with one thread and double it run by run, so a big list doesn't get rate limited right away
+    #[serde(skip)]
+    progress_channel: Option<Channel<ProgressPayload>>,  // progress goes here instead of the global event
+}
+
+impl Default for DownloadOptions {
+    fn default() -> Self {
+        Self {
+            threads: 4,
+            // Filled in with the Music folder when the download starts
+            download_path: String::new(),
+            format: "mp3".to_string(),
+            bitrate: None,
+            embed_plain_lyrics: false,
+            generate_synced_lrc: false,
+            lyrics_providers: Vec::new(),
+            audio_providers: Vec::new(),
+            save_metadata_file: None,
+            collection: None,
+            group_by_album: false,
+            fail_fast: false,
+            output_template: None,
+            generate_m3u: false,
+            overwrite: "skip".to_string(),
+            extra_spotdl_args: Vec::new(),
+            expected_tracks: None,
+            cookie_file: None,
+            per_track: false,
+            timeout_secs: None,
+            create_playlist_subfolder: false,
+            embed_album_art: true,
+            save_cover_file: false,
+            restrict_filenames: false,
+            skip_art_and_lyrics: false,
+            conversion_threads: None,
+            max_retries: 0,
+            delay_between_tracks_ms: None,
+            ramp_up: false,
+            progress_channel: None,
+        }
+    }
+}
+
+/// Download content from Spotify using spotdl, as a one-item run of the queue.
+/// Fields missing from `options` take their defaults
+#[tauri::command]
+async fn download_content(
+    app: AppHandle,
+    url: String,
+    options: DownloadOptions,
+    on_progress: Option<Channel<ProgressPayload>>,
+) -> Result<DownloadSummary, String> {
+    // A second click while downloading would otherwise start a run nobody can cancel
+    if app.state::<DownloadState>().busy.load(Ordering::SeqCst) {
+        return Err(BUSY_MESSAGE.to_string());
+    }
+
+    let options = DownloadOptions { progress_channel: on_progress, ..options };
+    // The link decides the content type, so a playlist can't be downloaded single-threaded as a "track"
+    let kind = validate_spotify_url(&url)?;
+    queue::run_now(&app, url.clone(), vec![url], kind, options)
+}
+
+/// Download only the songs that failed in the last download, with the same options
+#[tauri::command]
+async fn retry_failed(app: AppHandle, on_progress: Option<Channel<ProgressPayload>>) -> Result<DownloadSummary, String> {
+    let state = app.state::<DownloadState>();
+    if state.busy.load(Ordering::SeqCst) {
+        return Err(BUSY_MESSAGE.to_string());
+    }
+
+    // Kept until the retry finishes, which replaces it with whatever still fails, so a
+    // retry that never starts can be tried again
+    let failed = state.last_failed.lock()
+        .ok()
+        .and_then(|last_failed| last_failed.clone())
+        .ok_or("There are no failed tracks to retry")?;
+    let options = DownloadOptions { progress_channel: on_progress, ..failed.options };
+    queue::run_now(&app, failed.url, failed.tracks, failed.kind, options)
+}
+
+/// The download that was still running when the app last closed, None once it finished,
+/// was cancelled or is running again
+#[tauri::command]
+fn resume_interrupted(app: AppHandle, state: tauri::State<DownloadState>) -> Option<ResumeInfo> {
+    if state.busy.load(Ordering::SeqCst) {
+        return None;
+    }
+    resume::load(&app).as_ref().map(ResumeInfo::from)
+}
+
+/// Run the interrupted download again with its options. Songs it already
+/// finished are skipped as existing files
+#[tauri::command]
+async fn continue_interrupted(app: AppHandle, on_progress: Option<Channel<ProgressPayload>>) -> Result<DownloadSummary, String> {
+    if app.state::<DownloadState>().busy.load(Ordering::SeqCst) {
+        return Err(BUSY_MESSAGE.to_string());
+    }
+
+    let download = resume::load(&app).ok_or("There is no interrupted download to continue")?;
+    let options = DownloadOptions { progress_channel: on_progress, ..download.options };
+    queue::run_now(&app, download.url, download.queries, download.content_type, options)
+}
+
+/// Forget the interrupted download instead of continuing it
+#[tauri::command]
+fn discard_interrupted(app: AppHandle) -> Result<(), String> {
+    resume::clear(&app)
+}
+
+/// Run one download to completion; the queue worker calls this for every item.
+/// `queries` are what spotdl is given, normally just `url` but song names on a retry.
+fn run_download(
+    app: AppHandle,
+    url: String,
+    queries: Vec<String>,
+    kind: ContentKind,
+    options: DownloadOptions,
+) -> Result<DownloadSummary, String> {
+    let state = app.state::<DownloadState>();
+    let _busy = BusyGuard::acquire(&state)?;
+    let content_type = kind.as_str();
+    let retry_options = options.clone();
+    let requested_queries = queries.clone();
+
+    let DownloadOptions {
+        threads,
+        download_path,
+        format,
+        bitrate,
+        embed_plain_lyrics,
+        generate_synced_lrc,
+        lyrics_providers,
+        audio_providers,
+        save_metadata_file,
+        collection,
+        group_by_album,
+        fail_fast,
+        output_template,
+        generate_m3u,
+        overwrite,
+        extra_spotdl_args,
+        expected_tracks,
+        cookie_file,
+        per_track,
+        timeout_secs,
+        create_playlist_subfolder,
+        embed_album_art,
+        save_cover_file,
+        restrict_filenames,
+        skip_art_and_lyrics,
+        conversion_threads,
+        max_retries,
+        delay_between_tracks_ms,
+        ramp_up,
+        progress_channel,
+    } = options;
+    if let Ok(mut channel) = state.progress_channel.lock() {
+        *channel = progress_channel;
+    }
+
+    let format = format.trim().to_lowercase();
+    if !AUDIO_FORMATS.contains(&format.as_str()) {
+        return Err(format!(
+            "Unsupported audio format '{}'. Supported formats: {}",
+            format,
+            AUDIO_FORMATS.join(", ")
+        ));
+    }
+
+    let bitrate = bitrate.map(|b| b.trim().to_lowercase()).filter(|b| !b.is_empty());
+    if let Some(bitrate) = &bitrate {
+        if !BITRATES.contains(&bitrate.as_str()) {
+            return Err(format!(
+                "Invalid bitrate '{}'. Use auto, disable, a rate like 128k or 320k, or a VBR level 0-9",
+                bitrate
+            ));
+        }
+    }
+    let bitrate = bitrate.or_else(|| default_bitrate_for_format(&format).map(str::to_string));
+
+    // Validate lyrics options before spawning anything
+    if let Some(unknown) = lyrics_providers.iter().find(|p| !LYRICS_PROVIDERS.contains(&p.as_str())) {
+        return Err(format!(
+            "Unknown lyrics provider '{}'. Supported providers: {}",
+            unknown,
+            LYRICS_PROVIDERS.join(", ")
+        ));
+    }
+    if (embed_plain_lyrics || generate_synced_lrc) && lyrics_providers.is_empty() {
+        return Err("Select at least one lyrics provider to download lyrics".to_string());
+    }
+    if generate_synced_lrc && !lyrics_providers.iter().any(|p| p == "synced") {
+        return Err("Synced .lrc files require the 'synced' lyrics provider".to_string());
+    }
+
+    if let Some(unknown) = audio_providers.iter().find(|p| !AUDIO_PROVIDERS.contains(&p.as_str())) {
+        return Err(format!(
+            "Unknown audio provider '{}'. Supported providers: {}",
+            unknown,
+            AUDIO_PROVIDERS.join(", ")
+        ));
+    }
+
+    let overwrite = overwrite.trim().to_lowercase();
+    if !OVERWRITE_POLICIES.contains(&overwrite.as_str()) {
+        return Err(format!(
+            "Invalid overwrite policy '{}'. Use {}",
+            overwrite,
+            OVERWRITE_POLICIES.join(", ")
+        ));
+    }
+
+    // Arguments go to spotdl directly rather than through a shell, so only obvious mistakes are caught
+    if extra_spotdl_args.len() > MAX_EXTRA_ARGS {
+        return Err(format!("Too many extra spotdl arguments, the limit is {}", MAX_EXTRA_ARGS));
+    }
+    if extra_spotdl_args.iter().any(|arg| arg.trim().is_empty()) {
+        return Err("Extra spotdl arguments can't be empty".to_string());
+    }
+
+    let cookie_file = cookie_file.map(|f| f.trim().to_string()).filter(|f| !f.is_empty());
+    if let Some(file) = &cookie_file {
+        fs::File::open(file).map_err(|e| format!("Cookie file {} can't be read: {}", file, e))?;
+    }
+
+    if skip_art_and_lyrics && (embed_plain_lyrics || generate_synced_lrc) {
+        return Err("Lyrics can't be saved while art and lyrics are skipped, turn off one of them".to_string());
+    }
+    let embed_album_art = embed_album_art && !skip_art_and_lyrics;
+    // The folder cover is copied out of the songs' embedded art
+    if save_cover_file && !embed_album_art {
+        return Err("A cover.jpg is taken from the embedded album art, keep album art on to save one".to_string());
+    }
+
+    if conversion_threads.is_some_and(|n| !(1..=settings::MAX_THREADS).contains(&n)) {
+        return Err(format!("Conversion threads must be between 1 and {}", settings::MAX_THREADS));
+    }
+
+    if max_retries > MAX_RETRIES {
+        return Err(format!("Retries must be between 0 and {}", MAX_RETRIES));
+    }
+
+    if delay_between_tracks_ms.is_some_and(|ms| ms > MAX_TRACK_DELAY_MS) {
+        return Err(format!("The pause between tracks can be at most {}s", MAX_TRACK_DELAY_MS / 1000));
+    }
+
+    if timeout_secs == Some(0) {
+        return Err("The download timeout must be at least one second".to_string());
+    }
+
+    // A discography can be hundreds of songs, so the list has to be previewed and confirmed first.
+    // Retries pass song names instead of the artist link and are already bounded.
+    let artist_total = if kind == ContentKind::Artist && queries.contains(&url) {
+        Some(expected_tracks.ok_or(ARTIST_PREVIEW_MESSAGE)?)
+    } else {
+        None
+    };
+
+    // Each per-track run would overwrite these with its single song
+    if per_track && generate_m3u {
+        return Err("Playlist files need the whole list in one run, turn off per-track downloading to make one".to_string());
+    }
+    if per_track && save_metadata_file.as_deref().is_some_and(|f| !f.trim().is_empty()) {
+        return Err("A metadata save file needs the whole list in one run, turn off per-track downloading to write one".to_string());
+    }
+    if ramp_up && (generate_m3u || save_metadata_file.as_deref().is_some_and(|f| !f.trim().is_empty())) {
+        return Err("Playlist and metadata save files need the whole list in one run, turn off the thread ramp-up to make them".to_string());
+    }
+
+    // A download without its own template takes the one saved for its content type
+    let output_template = output_template.map(|t| t.trim().to_string()).filter(|t| !t.is_empty()).or_else(|| {
+        let settings = app.state::<Mutex<Settings>>();
+        let settings = settings.lock().ok()?;
+        settings.output_templates.for_kind(kind).filter(|_| !group_by_album).map(str::to_string)
+    });
+    if let Some(template) = &output_template {
+        validate_output_template(template)?;
+        if group_by_album {
+            return Err("Album grouping sets its own output template, turn it off to use a custom one".to_string());
+        }
+    }
+
+    // Reset cancelled state and progress left over from the previous download
+    state.is_cancelled.store(false, Ordering::SeqCst);
+    if let Ok(mut last) = state.last_progress.lock() {
+        *last = None;
+    }
+
+    // Quiet hours may have started since the queue took this download
+    if !quiet_hours::wait_until_over(&app, || state.is_cancelled.load(Ordering::SeqCst)) {
+        return Err(download_cancelled(&app));
+    }
+    
+    let download_path = if download_path.trim().is_empty() {
+        default_download_dir(&app).to_string_lossy().into_owned()
+    } else {
+        download_path
+    };
+
+    let spotdl_path = spotdl_path(&app)?;
+
+    // Keep a playlist's songs out of the shared Music folder, in a folder named after it.
+    // Retries use the song list's link too, so they land in the same folder.
+    let mut listed_songs = None;
+    let download_path = if create_playlist_subfolder && kind != ContentKind::Track {
+        let songs = fetch_download_songs(&app, &state, &spotdl_path, &url)?;
+        let name = songs.first()
+            .and_then(|song| match kind {
+                ContentKind::Album => Some(song.album_name.clone()),
+                _ => song.list_name.clone().or_else(|| song.artists.first().cloned()),
+            })
+            .filter(|name| !name.trim().is_empty())
+            .ok_or("Could not find the playlist's name to create its folder")?;
+        if queries == [url.as_str()] {
+            listed_songs = Some(songs);
+        }
+        Path::new(&download_path).join(metadata::folder_name(&name)).to_string_lossy().into_owned()
+    } else {
+        download_path
+    };
+
+    // Ensure download directory exists
+    let path = Path::new(&download_path);
+    disk_space::check_drive(path)?;
+    if !path.exists() {
+        fs::create_dir_all(path).map_err(|e| format!("Failed to create directory: {}", e))?;
+    }
+    disk_space::check_writable(path)?;
+    let mut cleanup = DownloadCleanup { app: &app, state: &state, finished: false };
+    *lock_recovering(&state.output_dir) = Some(path.to_path_buf());
+
+    let save_file = match save_metadata_file.as_deref().map(str::trim).filter(|f| !f.is_empty()) {
+        Some(file) => Some(resolve_save_file(path, file)?),
+        None => None,
+    };
+
+    let reserve_mb = app.state::<Mutex<Settings>>().lock().map(|s| s.min_free_space_mb).unwrap_or(0);
+    disk_space::check(path, &format, bitrate.as_deref(), expected_tracks, reserve_mb)?;
+
+    let _ = app.emit("download-started", DownloadStartedPayload {
+        url: url.clone(),
+        content_type: content_type.to_string(),
+        fail_fast,
+    });
+
+    // Out-of-range values would hand spotdl zero or hundreds of workers
+    let threads = threads.clamp(1, settings::MAX_THREADS);
+    // spotdl's threads both fetch and convert, it has no separate limit for ffmpeg,
+    // so lossless and other re-encoded formats get fewer of them
+    let threads = match conversion_threads {
+        Some(limit) if needs_transcoding(&format) => threads.min(limit),
+        _ => threads,
+    };
+    // Per-track downloads already run one song at a time
+    let ramp_up = ramp_up && !per_track && threads > 1;
+
+    // Emit starting progress
+    let message = match artist_total {
+        Some(total) => format!("Starting artist download of {} songs with {} parallel download(s), this can take a while...", total, threads),
+        None => format!("Starting download with {} parallel download(s)...", threads),
+    };
+    emit_progress(&app, ProgressPayload {
+        permille: START_PERMILLE,
+        percent: START_PERMILLE / 10,
+        message,
+        current_track: 0,
+        total_tracks: artist_total.unwrap_or(0),
+        speed: "".to_string(),
+        eta_seconds: None,
+        bytes_per_sec: None,
+        current_title: None,
+    });
+
+    let credentials = spotify_credentials(&app);
+    let proxy = proxy_setting(&app);
+
+    // Playlist file for music players, only meaningful for lists of songs
+    let generate_m3u = generate_m3u && matches!(kind, ContentKind::Playlist | ContentKind::Album);
+
+    // Split mixed playlists into one folder per album
+    let group_by_album = group_by_album && content_type == "playlist";
+
+    let ffmpeg_args = ffmpeg_args(&app);
+
+    // Build spotdl command with full path, for the given queries
+    let build_command = |queries: &[String], threads: u32| {
+        let mut cmd = spotdl_command(&app, &spotdl_path);
+        cmd.arg("--log-level").arg("INFO")
+            .arg("--log-format").arg(parser::LOG_FORMAT);
+        // A specific ffmpeg from the settings, spotdl's own lookup otherwise
+        cmd.args(&ffmpeg_args);
+        cmd.arg("--format").arg(&format);
+        if let Some(bitrate) = &bitrate {
+            cmd.arg("--bitrate").arg(bitrate);
+        }
+
+        // Before another flag, since spotdl's --restrict takes an optional value
+        // and an empty --lyrics list turns off its default lyrics lookup
+        if restrict_filenames {
+            cmd.arg("--restrict");
+        }
+        if skip_art_and_lyrics {
+            cmd.arg("--lyrics");
+        }
+        cmd.arg("--threads").arg(threads.to_string());
+        cmd.arg("--overwrite").arg(&overwrite);
+        if !embed_album_art {
+            cmd.arg("--skip-album-art");
+        }
+
+        // Lyrics are embedded from the chosen providers, synced ones can also go to .lrc files
+        if embed_plain_lyrics || generate_synced_lrc {
+            cmd.arg("--lyrics").args(&lyrics_providers);
+        }
+        if generate_synced_lrc {
+            cmd.arg("--generate-lrc");
+        }
+
+        // The user's own Spotify app, to avoid the shared credentials' rate limits
+        if let Some((id, secret)) = &credentials {
+            cmd.arg("--client-id").arg(id).arg("--client-secret").arg(secret);
+        }
+
+        // Route spotdl, and the yt-dlp and ffmpeg runs it starts, through the user's proxy
+        if let Some(proxy) = &proxy {
+            cmd.arg("--proxy").arg(proxy)
+                .env("HTTP_PROXY", proxy)
+                .env("HTTPS_PROXY", proxy);
+        }
+
+        // yt-dlp signs in with these cookies, for matches that are age-gated or members-only
+        if let Some(file) = &cookie_file {
+            cmd.arg("--cookie-file").arg(file);
+        }
+
+        // Audio sources in priority order, spotdl's own default when none are picked
+        if !audio_providers.is_empty() {
+            cmd.arg("--audio").args(&audio_providers);
+        }
+
+        // Keep spotdl's song metadata so the download can be repeated offline
+        if let Some(file) = &save_file {
+            cmd.arg("--save-file").arg(file);
+        }
+
+        if generate_m3u {
+            cmd.arg("--m3u").arg(M3U_TEMPLATE);
+        }
+
+        if group_by_album {
+            cmd.arg("--output").arg(ALBUM_OUTPUT_TEMPLATE);
+        } else if let Some(template) = &output_template {
+            cmd.arg("--output").arg(template);
+        }
+
+        cmd.args(queries);
+
+        // After the queries, so an option taking several values can't swallow them
+        cmd.args(&extra_spotdl_args);
+        cmd.current_dir(&download_path);
+
+        // Capture stdout and stderr
+        force_line_output(&mut cmd);
+        isolate_process_group(&mut cmd);
+        cmd.stdout(Stdio::piped());
+        cmd.stderr(Stdio::piped());
+        cmd
+    };
+
+    // The folder's manifest lists songs an earlier download already saved there, so only new
+    // ones go to spotdl. Forced overwrites and whole-list outputs still need every song.
+    let use_manifest = overwrite == "skip" && !generate_m3u && save_file.is_none() && queries == [url.as_str()];
+    let songs = if listed_songs.is_some() {
+        listed_songs
+    } else if per_track && queries.len() == 1 && kind != ContentKind::Track {
+        Some(fetch_download_songs(&app, &state, &spotdl_path, &queries[0])?)
+    } else if use_manifest || (ramp_up && queries.len() == 1 && kind != ContentKind::Track) {
+        // Without the song list the download still works, spotdl just checks every song itself
+        // and a ramp-up has nothing to split
+        match fetch_download_songs(&app, &state, &spotdl_path, &url) {
+            Err(e) if state.is_cancelled.load(Ordering::SeqCst) => return Err(e),
+            songs => songs.ok(),
+        }
+    } else {
+        None
+    };
+    let manifest = songs.as_ref().and(use_manifest.then(|| Manifest::load(path)));
+    let new_songs: Option<Vec<&metadata::SongMetadata>> = songs.as_ref().map(|songs| {
+        songs.iter().filter(|song| !manifest.as_ref().is_some_and(|m| m.has(path, song))).collect()
+    });
+    let already_downloaded = songs.as_ref().map_or(0, Vec::len) - new_songs.as_ref().map_or(0, Vec::len);
+    let queries = match &new_songs {
+        Some(new_songs) if already_downloaded > 0 || ramp_up => new_songs.iter().map(|song| song.url.clone()).collect(),
+        _ => queries,
+    };
+    let ramp_up = ramp_up && queries.len() > 1;
+
+    // In per-track mode every song gets its own spotdl run, so one can be skipped
+    let tracks: Vec<(String, String)> = match &new_songs {
+        _ if !per_track => Vec::new(),
+        Some(new_songs) => new_songs.iter().map(|song| (song.display_name(), song.url.clone())).collect(),
+        None => queries.iter().map(|query| (query.clone(), query.clone())).collect(),
+    };
+    state.per_track.store(per_track, Ordering::SeqCst);
+
+    // Songs the manifest skipped count as done from the start
+    let already_downloaded = already_downloaded as u32;
+    let total_tracks = if per_track {
+        already_downloaded + tracks.len() as u32
+    } else if already_downloaded > 0 || ramp_up {
+        already_downloaded + queries.len() as u32
+    } else {
+        artist_total.unwrap_or(1)
+    };
+    let started_at = SystemTime::now();
+
+    // Shared state for tracking progress
+    let log = app.state::<Mutex<Settings>>()
+        .lock()
+        .is_ok_and(|settings| settings.keep_download_logs)
+        .then(|| LogFile::create(&app).ok())
+        .flatten();
+    let log_path = log.as_ref().map(|log| log.path().to_path_buf());
+    let session = Arc::new(DownloadSession {
+        log,
+        secrets: credentials.iter()
+            .flat_map(|(id, secret)| [secret.clone(), id.clone()])
+            .chain(proxy.as_deref().and_then(proxy_credentials).map(str::to_string))
+            .collect(),
+        // An artist's previewed song count is known up front as well
+        fixed_total: per_track || ramp_up || artist_total.is_some() || already_downloaded > 0,
+        total_tracks: Mutex::new(total_tracks.max(1)),
+        current_track: Mutex::new(already_downloaded),
+        skipped: AtomicU32::new(already_downloaded),
+        manifest: manifest.map(|manifest| Mutex::new(manifest::Recorder::new(path, manifest, songs.as_deref().unwrap_or_default(), started_at))),
+        resume: resume::Tracker::start(&app, InterruptedDownload {
+            url: url.clone(),
+            queries: requested_queries,
+            content_type: kind,
+            options: retry_options.clone(),
+            output_folder: download_path.clone(),
+            started_at: chrono::Local::now().to_rfc3339(),
+            completed: Vec::new(),
+        }).map(Mutex::new),
+        ..DownloadSession::new(fail_fast, &format)
+    });
+
+    // Say so up front, so connection problems aren't a guessing game
+    if let Some(proxy) = &proxy {
+        let line = session.redact(format!("INFO|Using proxy {}", proxy));
+        session.log_line("app", &line);
+        emit_log_line(&line, &app, &session);
+    }
+
+    // Kills the download if it runs too long, stopped by dropping the sender once it is done
+    let watchdog = timeout_secs.map(|secs| spawn_watchdog(app.clone(), Arc::clone(&session), Duration::from_secs(secs.into())));
+
+    // Spawn the process
+    let succeeded = if per_track {
+        // One song at a time, so the concurrency is always one
+        let _ = app.emit("concurrency", ConcurrencyPayload { threads: 1 });
+        let total = tracks.len() as u32;
+        let mut succeeded = true;
+
+        // The same count later updates use, with the manifest's songs already done
+        let session_total = *lock_recovering(&session.total_tracks);
+        advance_permille(&mut lock_recovering(&session.last_permille), 100);
+        emit_progress(&app, ProgressPayload {
+            permille: 100,
+            percent: 10,
+            message: format!("Found {} song(s), downloading one at a time...", session_total),
+            current_track: already_downloaded,
+            total_tracks: session_total,
+            speed: "".to_string(),
+            eta_seconds: None,
+            bytes_per_sec: None,
+            current_title: None,
+        });
+
+        for (index, (label, query)) in tracks.iter().enumerate() {
+            let stop_early = session.fail_fast && session.first_failure.lock().is_ok_and(|f| f.is_some());
+            if state.is_cancelled.load(Ordering::SeqCst) || session.timed_out.load(Ordering::SeqCst) || stop_early {
+                break;
+            }
+
+            if let Some(delay_ms) = delay_between_tracks_ms.filter(|ms| *ms > 0 && index > 0) {
+                let _ = app.emit("throttling", ThrottlingPayload { next_track: label.clone(), index: index as u32 + 1, total, delay_ms });
+                if !pause_unless_stopped(&state, &session, Duration::from_millis(delay_ms.into())) {
+                    break;
+                }
+            }
+
+            session.set_current_title(label);
+            let _ = app.emit("track-started", TrackStartedPayload { track: label.clone(), index: index as u32 + 1, total });
+            let mut attempt = 0;
+            let status = loop {
+                let failed_before = lock_recovering(&session.failed_tracks).len();
+                let had_first_failure = lock_recovering(&session.first_failure).is_some();
+                session.transient_failure.store(false, Ordering::SeqCst);
+                let status = run_spotdl(&app, &state, build_command(std::slice::from_ref(query), threads), &session)?;
+
+                // spotdl can exit cleanly with the song failed, so its failure lines count too
+                let failed = !status.success() || lock_recovering(&session.failed_tracks).len() > failed_before;
+                let stopped = state.is_cancelled.load(Ordering::SeqCst) || state.skip_requested.load(Ordering::SeqCst);
+                if !failed || stopped || attempt == max_retries || !session.transient_failure.load(Ordering::SeqCst) {
+                    if attempt > 0 && !failed {
+                        session.recovered.fetch_add(1, Ordering::SeqCst);
+                    }
+                    break status;
+                }
+
+                attempt += 1;
+                let delay = retry_delay(attempt);
+                let line = format!("WARNING|{} hit a temporary error, retrying in {}s ({} of {})", label, delay.as_secs(), attempt, max_retries);
+                session.log_line("app", &line);
+                emit_log_line(&line, &app, &session);
+                if !pause_unless_stopped(&state, &session, delay) {
+                    break status;
+                }
+
+                // The retry decides whether the song failed
+                lock_recovering(&session.failed_tracks).truncate(failed_before);
+                if !had_first_failure {
+                    *lock_recovering(&session.first_failure) = None;
+                }
+            };
+
+            // A skipped song counts like one spotdl skipped itself
+            if state.skip_requested.swap(false, Ordering::SeqCst) {
+                let update = apply_event(&session, &SpotdlEvent::Skipped { track: label.clone() });
+                if let Some(track) = update.track {
+                    let _ = app.emit("track-complete", track);
+                }
+                if let Some(payload) = update.progress {
+                    emit_progress(&app, payload);
+                }
+            } else if !status.success() {
+                succeeded = false;
+            }
+        }
+        succeeded
+    } else if queries.is_empty() {
+        // Every song is in the manifest, there is nothing for spotdl to do
+        true
+    } else if state.is_cancelled.load(Ordering::SeqCst) {
+        // Cancelled after the song list was looked up, before spotdl started
+        false
+    } else if ramp_up {
+        // spotdl fixes its worker count per run, so the list goes in runs with more threads each time
+        let mut succeeded = true;
+        let mut rest = queries.as_slice();
+        for (batch_threads, size) in ramp_batches(queries.len(), threads) {
+            if state.is_cancelled.load(Ordering::SeqCst) || session.timed_out.load(Ordering::SeqCst) {
+                break;
+            }
+            let (batch, remaining) = rest.split_at(size);
+            rest = remaining;
+            let _ = app.emit("concurrency", ConcurrencyPayload { threads: batch_threads });
+            succeeded &= run_spotdl(&app, &state, build_command(batch, batch_threads), &session)?.success();
         }
+        succeeded
+    } else {
+        // spotdl fixes its worker count for the whole run, so this is the concurrency throughout
+        let _ = app.emit("concurrency", ConcurrencyPayload { threads });
+        run_spotdl(&app, &state, build_command(&queries, threads), &session)?.success()
+    };
+
+    drop(watchdog);
+    let timed_out = session.timed_out.load(Ordering::SeqCst);
+    let succeeded = succeeded && !timed_out;
+
+    cleanup.finished = true;
+    drop(cleanup);
+
+    // Check if cancelled
+    // Songs saved before the cancel are kept, so say how many made it
+    if state.is_cancelled.load(Ordering::SeqCst) {
+        let _ = resume::clear(&app);
+        download_cancelled(&app);
+        let saved = *lock_recovering(&session.current_track);
+        let total = *lock_recovering(&session.total_tracks);
+        let message = match saved {
+            0 => "Download cancelled before any songs were saved".to_string(),
+            saved => format!("Download cancelled, {} of {} songs were saved", saved, total.max(saved)),
+        };
+        let counts = session.counts();
+        let _ = app.emit("download-summary", session.summary_event());
+        return Ok(DownloadSummary {
+            message: with_log_path(message, log_path.as_deref()),
+            counts,
+            output_folder: download_path,
+            cancelled: true,
+            match_warnings: lock_recovering(&session.match_warnings).clone(),
+        });
     }
-}
 
-#[derive(Clone, Serialize)]
-struct ProgressPayload {
-    percent: u32,
-    message: String,
-    current_track: u32,
-    total_tracks: u32,
-    speed: String,  // e.g., "2.5 songs/min"
-}
+    // Keep the songs that failed so retry_failed can fetch just those
+    let failed = session.failed_tracks.lock().map(|f| f.clone()).unwrap_or_default();
+    if let Ok(mut last_failed) = state.last_failed.lock() {
+        *last_failed = (!failed.is_empty()).then(|| FailedDownload {
+            url: url.clone(),
+            kind,
+            options: retry_options,
+            tracks: failed,
+        });
+    }
+    
+    // Calculate final speed (handle potential poisoned mutex)
+    let final_current = *lock_recovering(&session.current_track);
+    let final_total = *lock_recovering(&session.total_tracks);
+    let elapsed_secs = session.start_time.elapsed().as_secs_f64();
+    let final_speed = if final_current > 0 { compute_speed(final_current, elapsed_secs) } else { String::new() };
 
-/// Cancel the current download
-#[tauri::command]
-fn cancel_download(state: tauri::State<DownloadState>) -> Result<(), String> {
-    let pid = state.child_pid.load(Ordering::SeqCst);
-    if pid > 0 {
-        state.is_cancelled.store(true, Ordering::SeqCst);
-        
-        // Kill the process group
-        let _ = Command::new("kill")
-            .arg("-TERM")
-            .arg(format!("-{}", pid))
-            .output();
-        
-        // Also kill the process directly as fallback
-        let _ = Command::new("kill")
-            .arg("-TERM")
-            .arg(pid.to_string())
-            .output();
-        
-        state.child_pid.store(0, Ordering::SeqCst);
-        Ok(())
+    // Tracks without album data end up at the top level, gather them in their own folder
+    let album_folders = if group_by_album {
+        collect_unknown_album_tracks(path, started_at);
+        album_folders(path, started_at)
     } else {
-        Err("No active download to cancel".to_string())
-    }
-}
+        Vec::new()
+    };
 
-/// Helper function to process output lines
-fn process_output_line(
-    line: &str,
-    app: &AppHandle,
-    current_track: &Arc<Mutex<u32>>,
-    total_tracks: &Arc<Mutex<u32>>,
-    last_percent: &Arc<Mutex<u32>>,
-    start_time: &Instant,
-) {
-    let message = line.trim();
-    if message.is_empty() {
-        return;
+    // Report which lyric types each new track ended up with
+    if embed_plain_lyrics || generate_synced_lrc {
+        let missing = session.missing_lyrics.lock().map(|m| m.clone()).unwrap_or_default();
+        let report = lyrics_report(path, started_at, embed_plain_lyrics, &missing);
+        let _ = app.emit("lyrics-report", report);
     }
 
-    // Use unwrap_or_else to handle poisoned mutexes gracefully
-    let Ok(mut current) = current_track.lock() else { return };
-    let Ok(mut total) = total_tracks.lock() else { return };
-    let Ok(mut last_pct) = last_percent.lock() else { return };
+    let m3u_file = if generate_m3u { newest_playlist_file(path, started_at) } else { None };
 
-    // Calculate download speed
-    let elapsed_secs = start_time.elapsed().as_secs_f64();
-    let speed = if *current > 0 && elapsed_secs > 0.0 {
-        let songs_per_min = (*current as f64 / elapsed_secs) * 60.0;
-        if songs_per_min >= 1.0 {
-            format!("{:.1} songs/min", songs_per_min)
-        } else {
-            let secs_per_song = elapsed_secs / *current as f64;
-            format!("{:.0}s/song", secs_per_song)
+    // Media servers look for a cover file next to the songs
+    if save_cover_file {
+        for folder in library::save_folder_covers(path, started_at) {
+            let line = format!("WARNING|No embedded album art in {}, no cover file was saved", folder.display());
+            session.log_line("app", &line);
+            emit_log_line(&line, &app, &session);
         }
+    }
+
+    let error_kind = if succeeded || timed_out {
+        None
+    } else if session.ffmpeg_missing.load(Ordering::SeqCst) {
+        Some(ErrorKind::Ffmpeg)
     } else {
-        "calculating...".to_string()
+        *lock_recovering(&session.error_kind)
     };
 
-    // Check for "Found X songs" or "Processing query" patterns
-    if (message.contains("Found") && message.contains("song")) || message.contains("Processing query") {
-        if let Some(count) = extract_number(message) {
-            *total = count.max(1);
-            let _ = app.emit("download-progress", ProgressPayload {
-                percent: 10,
-                message: format!("Found {} song(s), starting download...", *total),
-                current_track: 0,
-                total_tracks: *total,
-                speed: "".to_string(),
-            });
-            *last_pct = 10;
-        }
+    let _ = app.emit("download-finished", DownloadFinishedPayload {
+        success: succeeded,
+        save_file: save_file.filter(|file| file.exists()).map(|file| file.to_string_lossy().into_owned()),
+        region_locked: session.region_locked.lock().map(|l| l.clone()).unwrap_or_default(),
+        album_folders,
+        m3u_file: m3u_file.as_ref().map(|file| file.to_string_lossy().into_owned()),
+        error_kind,
+    });
+
+    // spotdl got through every song, so there is nothing left to resume. Songs that failed
+    // are offered by retry_failed instead
+    let stopped_early = timed_out || (fail_fast && session.first_failure.lock().is_ok_and(|f| f.is_some()));
+    if succeeded || !stopped_early {
+        let _ = resume::clear(&app);
     }
-    // Check for download progress indicators
-    else if message.contains("Downloaded") {
-        *current += 1;
-        
-        // Calculate progress: 10% for finding, 10-95% for downloading
-        let download_progress = if *total > 0 {
-            ((*current as f32 / *total as f32) * 85.0) as u32
-        } else {
-            50
-        };
-        let percent = (10 + download_progress).min(95);
-        
-        if percent > *last_pct {
-            *last_pct = percent;
-            let _ = app.emit("download-progress", ProgressPayload {
-                percent,
-                message: "Downloading...".to_string(),
-                current_track: *current,
-                total_tracks: *total,
-                speed: speed.clone(),
-            });
+
+    let result = if succeeded {
+        let counts = session.counts();
+        let _ = history::append(&app, HistoryEntry {
+            url: url.clone(),
+            content_type: content_type.to_string(),
+            collection: collection.map(|c| c.trim().to_string()).filter(|c| !c.is_empty()),
+            output_path: download_path.clone(),
+            timestamp: chrono::Local::now().to_rfc3339(),
+            track_count: counts.downloaded + counts.skipped,
+            format: Some(format.clone()),
+        });
+
+        emit_progress(&app, ProgressPayload {
+            permille: 1000,
+            percent: 100,
+            message: "Download complete!".to_string(),
+            current_track: final_total,
+            total_tracks: final_total,
+            speed: final_speed,
+            eta_seconds: Some(0),
+            bytes_per_sec: None,
+            current_title: None,
+        });
+        match &m3u_file {
+            Some(file) => Ok(format!(
+                "{} downloaded successfully! {}. Playlist file: {}",
+                capitalize(content_type),
+                counts,
+                file.display()
+            )),
+            None => Ok(format!("{} downloaded successfully! {}.", capitalize(content_type), counts)),
         }
-    }
-    // Check for "Skipping" messages (already downloaded)
-    else if message.contains("Skipping") {
-        *current += 1;
-        
-        let download_progress = if *total > 0 {
-            ((*current as f32 / *total as f32) * 85.0) as u32
-        } else {
-            50
-        };
-        let percent = (10 + download_progress).min(95);
-        
-        if percent > *last_pct {
-            *last_pct = percent;
-            let _ = app.emit("download-progress", ProgressPayload {
-                percent,
-                message: "Processing...".to_string(),
-                current_track: *current,
-                total_tracks: *total,
-                speed: speed.clone(),
-            });
+    } else if let Some(secs) = timeout_secs.filter(|_| timed_out) {
+        Err(format!("Download timed out after {} seconds", secs))
+    } else if session.ffmpeg_missing.load(Ordering::SeqCst) {
+        Err(FFMPEG_INSTALL_HINT.to_string())
+    } else if let Some(track) = session.first_failure.lock().ok().and_then(|f| f.clone()).filter(|_| fail_fast) {
+        Err(format!("Download stopped at the first failed track: {}", track))
+    } else {
+        let errors = session.recent_errors.lock().map(|e| Vec::from(e.clone())).unwrap_or_default();
+        match (error_kind, errors.is_empty()) {
+            (Some(kind), true) => Err(format!("Download failed. {}", kind.message())),
+            (Some(kind), false) => Err(format!("Download failed. {} Details: {}", kind.message(), errors.join(" | "))),
+            (None, true) => Err("Download failed. Please check the URL and try again.".to_string()),
+            (None, false) => Err(format!("Download failed: {}", errors.join(" | "))),
         }
+    };
+    notify_finished(&app, &result, final_current);
+
+    let counts = session.counts();
+    let _ = app.emit("download-summary", session.summary_event());
+    result
+        .map(|message| DownloadSummary {
+            message: with_log_path(message, log_path.as_deref()),
+            counts,
+            output_folder: download_path,
+            cancelled: false,
+            match_warnings: lock_recovering(&session.match_warnings).clone(),
+        })
+        .map_err(|error| with_log_path(error, log_path.as_deref()))
+}
+
+/// Split a ramped-up download of `songs` into (threads, songs) runs, starting at one thread
+/// and doubling up to `threads` after RAMP_BATCH_ROUNDS songs per thread, with the rest in the last run
+fn ramp_batches(songs: usize, threads: u32) -> Vec<(u32, usize)> {
+    let mut batches = Vec::new();
+    let (mut current, mut left) = (1, songs);
+    while current < threads && left > 0 {
+        let size = (current as usize * RAMP_BATCH_ROUNDS).min(left);
+        batches.push((current, size));
+        left -= size;
+        current = (current * 2).min(threads);
     }
-    // Check for conversion/processing
-    else if message.contains("Converting") || message.contains("Processing") {
-        let _ = app.emit("download-progress", ProgressPayload {
-            percent: (*last_pct).max(90),
-            message: "Converting to MP3...".to_string(),
-            current_track: *current,
-            total_tracks: *total,
-            speed: speed.clone(),
-        });
+    if left > 0 {
+        batches.push((threads, left));
     }
+    batches
 }
 
-/// Get the default download path (~/Music)
-#[tauri::command]
-fn get_download_path() -> String {
-    let home = std::env::var("HOME").unwrap_or_else(|_| ".".to_string());
-    let music_path = format!("{}/Music", home);
-    
-    // Create directory if it doesn't exist
-    let _ = fs::create_dir_all(&music_path);
-    
-    music_path
+/// How long to wait before a song's `attempt`th retry, doubling each time
+fn retry_delay(attempt: u32) -> Duration {
+    (RETRY_BASE_DELAY * 2u32.saturating_pow(attempt - 1)).min(RETRY_MAX_DELAY)
 }
 
-/// Download content from Spotify using spotdl
-#[tauri::command]
-async fn download_content(
-    app: AppHandle,
-    url: String,
-    content_type: String,
-    threads: u32,
-    download_path: String,
-    state: tauri::State<'_, DownloadState>,
-) -> Result<String, String> {
-    // Reset cancelled state
-    state.is_cancelled.store(false, Ordering::SeqCst);
-    
-    // Ensure download directory exists
-    let path = Path::new(&download_path);
-    if !path.exists() {
-        fs::create_dir_all(path).map_err(|e| format!("Failed to create directory: {}", e))?;
+/// Sleep before a retry or the next song, false when the download was cancelled or timed out meanwhile
+fn pause_unless_stopped(state: &DownloadState, session: &DownloadSession, delay: Duration) -> bool {
+    let deadline = Instant::now() + delay;
+    while Instant::now() < deadline {
+        if state.is_cancelled.load(Ordering::SeqCst) || session.timed_out.load(Ordering::SeqCst) {
+            return false;
+        }
+        thread::sleep(Duration::from_millis(100));
     }
+    true
+}
 
-    // Get home directory for spotdl path
-    let home = std::env::var("HOME").unwrap_or_else(|_| ".".to_string());
-    let spotdl_path = format!("{}/.venv/bin/spotdl", home);
+/// Kill whatever spotdl process the download is running once `timeout` passes,
+/// unless the returned sender is dropped first
+fn spawn_watchdog(app: AppHandle, session: Arc<DownloadSession>, timeout: Duration) -> mpsc::Sender<()> {
+    let (sender, receiver) = mpsc::channel::<()>();
+    thread::spawn(move || {
+        if receiver.recv_timeout(timeout) != Err(mpsc::RecvTimeoutError::Timeout) {
+            return;
+        }
 
-    // Emit starting progress
-    let _ = app.emit("download-progress", ProgressPayload {
-        percent: 5,
-        message: "Starting download...".to_string(),
+        session.timed_out.store(true, Ordering::SeqCst);
+        let pid = app.state::<DownloadState>().child_pid.load(Ordering::SeqCst);
+        if pid > 0 {
+            kill_process(pid);
+        }
+    });
+    sender
+}
+
+/// Tell the UI the download was cancelled, returns the error the command ends with
+fn download_cancelled(app: &AppHandle) -> String {
+    emit_progress(app, ProgressPayload {
+        permille: 0,
+        percent: 0,
+        message: "Download cancelled".to_string(),
         current_track: 0,
         total_tracks: 0,
         speed: "".to_string(),
+        eta_seconds: None,
+        bytes_per_sec: None,
+        current_title: None,
     });
+    "Download cancelled by user".to_string()
+}
 
-    // Build spotdl command with full path
-    let mut cmd = Command::new(&spotdl_path);
-    cmd.arg("--format").arg("mp3");
+/// Start spotdl and store its PID right away, so cancel and pause reach it from the start
+fn spawn_tracked(cmd: &mut Command, state: &DownloadState) -> Result<Child, String> {
+    let child = cmd.spawn().map_err(|e| format!("Failed to run spotdl: {}", e))?;
+    let pid = child.id();
+    state.child_pid.store(pid, Ordering::SeqCst);
 
-    // Add threads for playlists and albums
-    if content_type == "playlist" || content_type == "album" {
-        cmd.arg("--threads").arg(threads.to_string());
+    // A per-track download paused between songs keeps the next one paused too
+    if state.pause_reasons.load(Ordering::SeqCst) != 0 {
+        send_signal(pid, "STOP");
     }
+    Ok(child)
+}
 
-    cmd.arg(&url);
-    cmd.current_dir(&download_path);
-    
-    // Capture stdout and stderr
-    cmd.stdout(Stdio::piped());
-    cmd.stderr(Stdio::piped());
+/// Look up a download's songs with a spotdl run that can be cancelled like the download itself
+fn fetch_download_songs(app: &AppHandle, state: &DownloadState, spotdl_path: &Path, url: &str) -> Result<Vec<metadata::SongMetadata>, String> {
+    let songs = fetch_songs_tracked(app, state, spotdl_path, url);
+    if state.is_cancelled.load(Ordering::SeqCst) {
+        return Err(download_cancelled(app));
+    }
+    songs
+}
+
+/// Look up a URL's songs with the spotdl process registered, so cancel_download can stop it
+fn fetch_songs_tracked(app: &AppHandle, state: &DownloadState, spotdl_path: &Path, url: &str) -> Result<Vec<metadata::SongMetadata>, String> {
+    let args = [credential_args(app), proxy_args(app)].concat();
+    metadata::fetch_songs_with(spotdl_command(app, spotdl_path), url, &args, |cmd| {
+        cmd.stdout(Stdio::null()).stderr(Stdio::null());
+        isolate_process_group(cmd);
+        let mut child = spawn_tracked(cmd, state)?;
+        let status = wait_for_exit(&mut child, state);
+        state.child_pid.store(0, Ordering::SeqCst);
+        status
+    })
+}
+
+/// Run one spotdl process to completion, feeding its output to the session
+fn run_spotdl(app: &AppHandle, state: &DownloadState, mut cmd: Command, session: &Arc<DownloadSession>) -> Result<ExitStatus, String> {
+    let mut child = spawn_tracked(&mut cmd, state)?;
 
-    // Spawn the process
-    let mut child = cmd.spawn().map_err(|e| format!("Failed to run spotdl: {}", e))?;
-    
-    // Store the child PID
-    let pid = child.id();
-    state.child_pid.store(pid, Ordering::SeqCst);
-    
     // Get stdout and stderr
     let stdout = child.stdout.take().ok_or("Failed to capture stdout")?;
     let stderr = child.stderr.take().ok_or("Failed to capture stderr")?;
-    
-    // Shared state for tracking progress
-    let current_track = Arc::new(Mutex::new(0u32));
-    let total_tracks = Arc::new(Mutex::new(1u32));
-    let last_percent = Arc::new(Mutex::new(5u32));
-    let start_time = Instant::now();
-    
+
     // Clone app handle and shared state for threads
     let app_stdout = app.clone();
-    let current_stdout = Arc::clone(&current_track);
-    let total_stdout = Arc::clone(&total_tracks);
-    let last_pct_stdout = Arc::clone(&last_percent);
-    let start_stdout = start_time.clone();
-    
+    let session_stdout = Arc::clone(session);
+
     let app_stderr = app.clone();
-    let current_stderr = Arc::clone(&current_track);
-    let total_stderr = Arc::clone(&total_tracks);
-    let last_pct_stderr = Arc::clone(&last_percent);
-    let start_stderr = start_time.clone();
-    
+    let session_stderr = Arc::clone(session);
+
     // Spawn threads to read stdout and stderr
-    // When process is killed, pipes close and threads exit naturally
+    // When process is killed, pipes close and threads exit naturally.
+    // Output of a cancelled download is no longer processed
     let stdout_handle = thread::spawn(move || {
         let reader = BufReader::new(stdout);
-        for line in reader.lines() {
-            if let Ok(line) = line {
-                process_output_line(
-                    &line,
-                    &app_stdout,
-                    &current_stdout,
-                    &total_stdout,
-                    &last_pct_stdout,
-                    &start_stdout,
-                );
+        for line in reader.lines().map_while(Result::ok) {
+            if app_stdout.state::<DownloadState>().is_cancelled.load(Ordering::SeqCst) {
+                break;
             }
+            let line = session_stdout.redact(line);
+            session_stdout.log_line("stdout", &line);
+            process_output_line(&line, &app_stdout, &session_stdout);
         }
     });
-    
+
     let stderr_handle = thread::spawn(move || {
         let reader = BufReader::new(stderr);
-        for line in reader.lines() {
-            if let Ok(line) = line {
-                process_output_line(
-                    &line,
-                    &app_stderr,
-                    &current_stderr,
-                    &total_stderr,
-                    &last_pct_stderr,
-                    &start_stderr,
-                );
+        for line in reader.lines().map_while(Result::ok) {
+            if app_stderr.state::<DownloadState>().is_cancelled.load(Ordering::SeqCst) {
+                break;
             }
+            let line = session_stderr.redact(line);
+            session_stderr.log_line("stderr", &line);
+            emit_log_line(&line, &app_stderr, &session_stderr);
+            process_output_line(&line, &app_stderr, &session_stderr);
         }
     });
-    
-    // Wait for the process to complete
-    let status = child.wait().map_err(|e| format!("Failed to wait for spotdl: {}", e))?;
-    
+
+    // Wait for the process to complete, sending throttled progress as its interval passes
+    let status = wait_for_exit_with(&mut child, state, || flush_progress(app, session));
+
     // Wait for reader threads to finish
     let _ = stdout_handle.join();
     let _ = stderr_handle.join();
-    
-    // Clear the child PID
-    state.child_pid.store(0, Ordering::SeqCst);
-    
-    // Check if cancelled
-    if state.is_cancelled.load(Ordering::SeqCst) {
-        let _ = app.emit("download-progress", ProgressPayload {
-            percent: 0,
-            message: "Download cancelled".to_string(),
-            current_track: 0,
-            total_tracks: 0,
-            speed: "".to_string(),
-        });
-        return Err("Download cancelled by user".to_string());
+    let pending = lock_recovering(&session.pending_progress).take();
+    if let Some(payload) = pending {
+        emit_progress(app, payload);
     }
-    
-    // Calculate final speed (handle potential poisoned mutex)
-    let final_current = current_track.lock().map(|c| *c).unwrap_or(0);
-    let final_total = total_tracks.lock().map(|t| *t).unwrap_or(1);
-    let elapsed_secs = start_time.elapsed().as_secs_f64();
-    let final_speed = if final_current > 0 && elapsed_secs > 0.0 {
-        let songs_per_min = (final_current as f64 / elapsed_secs) * 60.0;
-        if songs_per_min >= 1.0 {
-            format!("{:.1} songs/min", songs_per_min)
-        } else {
-            let secs_per_song = elapsed_secs / final_current as f64;
-            format!("{:.0}s/song", secs_per_song)
-        }
-    } else {
-        "".to_string()
+    status
+}
+
+/// Desktop notification for a finished download, when the user opted in
+fn notify_finished(app: &AppHandle, result: &Result<String, String>, tracks: u32) {
+    let enabled = app.state::<Mutex<Settings>>().lock().is_ok_and(|settings| settings.notify_on_complete);
+    if !enabled {
+        return;
+    }
+
+    let (title, body) = match result {
+        Ok(_) => ("Download complete", format!("{} track(s) downloaded", tracks)),
+        Err(error) => ("Download failed", error.clone()),
     };
+    let _ = app.notification().builder().title(title).body(body).show();
+}
 
-    if status.success() {
-        let _ = app.emit("download-progress", ProgressPayload {
-            percent: 100,
-            message: "Download complete!".to_string(),
-            current_track: final_total,
-            total_tracks: final_total,
-            speed: final_speed,
-        });
-        Ok(format!("{} downloaded successfully!", capitalize(&content_type)))
-    } else {
-        Err("Download failed. Please check the URL and try again.".to_string())
+/// Resolve a .spotdl save file path, relative ones are placed in the download folder
+fn resolve_save_file(download_dir: &Path, file: &str) -> Result<PathBuf, String> {
+    let save_file = download_dir.join(file);
+    if save_file.extension().and_then(|ext| ext.to_str()) != Some("spotdl") {
+        return Err("The metadata save file must end with .spotdl".to_string());
+    }
+
+    match save_file.parent() {
+        Some(dir) if dir.is_dir() => Ok(save_file),
+        _ => Err(format!("Directory for the metadata save file does not exist: {}", save_file.display())),
+    }
+}
+
+/// Move new tracks that landed directly in `dir` into the "Unknown Album" folder
+fn collect_unknown_album_tracks(dir: &Path, since: SystemTime) {
+    let unknown_dir = dir.join(UNKNOWN_ALBUM_DIR);
+    let loose_tracks: Vec<PathBuf> = recent_audio_files(dir, since)
+        .into_iter()
+        .filter(|file| file.parent() == Some(dir))
+        .collect();
+    if loose_tracks.is_empty() || fs::create_dir_all(&unknown_dir).is_err() {
+        return;
+    }
+
+    for track in loose_tracks {
+        // Keep a synced lyrics file next to its track
+        for file in [track.with_extension("lrc"), track] {
+            if let Some(name) = file.file_name() {
+                if file.exists() {
+                    let _ = fs::rename(&file, unknown_dir.join(name));
+                }
+            }
+        }
+    }
+}
+
+/// Album folders (relative to `dir`) that received new tracks
+fn album_folders(dir: &Path, since: SystemTime) -> Vec<String> {
+    let folders: BTreeSet<String> = recent_audio_files(dir, since)
+        .iter()
+        .filter_map(|file| file.parent()?.strip_prefix(dir).ok())
+        .filter(|folder| !folder.as_os_str().is_empty())
+        .map(|folder| folder.to_string_lossy().into_owned())
+        .collect();
+    folders.into_iter().collect()
+}
+
+/// Build the per-track lyrics report for audio files written since `since`
+fn lyrics_report(dir: &Path, since: SystemTime, embed_plain: bool, missing: &[String]) -> Vec<LyricsStatus> {
+    recent_audio_files(dir, since)
+        .into_iter()
+        .map(|file| {
+            let track = file.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+            let plain = embed_plain && !missing.iter().any(|m| track.contains(m.as_str()));
+            let synced = file.with_extension("lrc").exists();
+            LyricsStatus { track, plain, synced }
+        })
+        .collect()
+}
+
+/// Recursively list audio files under `dir` modified at or after `since`
+fn recent_audio_files(dir: &Path, since: SystemTime) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let Ok(entries) = fs::read_dir(dir) else { return files };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            files.extend(recent_audio_files(&path, since));
+            continue;
+        }
+
+        let is_audio = path.extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| AUDIO_FORMATS.contains(&ext.to_lowercase().as_str()));
+        let is_recent = entry.metadata()
+            .and_then(|meta| meta.modified())
+            .is_ok_and(|modified| modified >= since);
+        if is_audio && is_recent {
+            files.push(path);
+        }
     }
+    files
 }
 
-fn extract_number(s: &str) -> Option<u32> {
-    s.split_whitespace()
-        .find_map(|word| word.parse::<u32>().ok())
+/// The .m3u/.m3u8 file spotdl wrote into `dir` during this download, if any
+fn newest_playlist_file(dir: &Path, since: SystemTime) -> Option<PathBuf> {
+    fs::read_dir(dir)
+        .ok()?
+        .flatten()
+        .filter(|entry| {
+            entry.path()
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("m3u8") || ext.eq_ignore_ascii_case("m3u"))
+        })
+        .filter_map(|entry| Some((entry.metadata().ok()?.modified().ok()?, entry.path())))
+        .filter(|(modified, _)| *modified >= since)
+        .max_by_key(|(modified, _)| *modified)
+        .map(|(_, path)| path)
 }
 
 fn capitalize(s: &str) -> String {
@@ -357,12 +3848,363 @@ pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_dialog::init())
+        .plugin(tauri_plugin_notification::init())
         .manage(DownloadState::default())
+        .manage(ProgressServer::default())
+        .manage(DownloadQueue::default())
+        .setup(|app| {
+            let settings = settings::load(app.handle()).unwrap_or_default();
+            app.manage(Mutex::new(settings));
+            quiet_hours::spawn_scheduler(app.handle().clone());
+            thermal::spawn_monitor(app.handle().clone());
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             get_download_path,
+            open_download_folder,
+            get_free_space,
+            list_downloads,
+            verify_downloads,
+            move_downloads,
+            delete_download,
             download_content,
-            cancel_download
+            cancel_download,
+            cancel_all,
+            skip_current_track,
+            start_progress_server,
+            stop_progress_server,
+            get_quiet_hours,
+            set_quiet_hours,
+            repair_settings,
+            get_thermal_guard,
+            set_thermal_guard,
+            get_cpu_temperature,
+            get_history,
+            clear_history,
+            get_collections,
+            get_current_progress,
+            get_slow_download_config,
+            set_slow_download_config,
+            benchmark_threads,
+            run_self_test,
+            reconcile,
+            sync_playlist,
+            retag,
+            download_single,
+            get_spotdl_path,
+            set_spotdl_path,
+            install_spotdl,
+            check_dependencies,
+            get_app_version,
+            load_settings,
+            save_settings,
+            enqueue_download,
+            import_url_list,
+            dequeue_download,
+            move_queued_download,
+            list_queue,
+            detect_content_type,
+            bitrate_presets,
+            normalize_url,
+            run_spotdl_debug,
+            retry_failed,
+            resume_interrupted,
+            continue_interrupted,
+            discard_interrupted,
+            pause_download,
+            resume_download,
+            preview_tracks,
+            estimate_download_size,
+            test_spotify_credentials
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::OsStr;
+
+    #[test]
+    fn spotdl_output_is_unbuffered() {
+        let mut cmd = Command::new("spotdl");
+        force_line_output(&mut cmd);
+
+        let unbuffered = cmd.get_envs().find(|(key, _)| *key == OsStr::new("PYTHONUNBUFFERED"));
+        assert_eq!(unbuffered, Some((OsStr::new("PYTHONUNBUFFERED"), Some(OsStr::new("1")))));
+    }
+
+    #[test]
+    fn capitalizes_first_char() {
+        assert_eq!(capitalize(""), "");
+        assert_eq!(capitalize("a"), "A");
+        assert_eq!(capitalize("Playlist"), "Playlist");
+        assert_eq!(capitalize("album"), "Album");
+        assert_eq!(capitalize("école"), "École");
+        // Some characters uppercase to more than one
+        assert_eq!(capitalize("ßtraße"), "SStraße");
+    }
+
+    #[test]
+    fn deleting_a_song_drops_it_from_parent_manifests() {
+        let root = std::env::temp_dir().join(format!("spotify-downloader-delete-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("Road Trip")).unwrap();
+        let song = root.join("Road Trip/Daft Punk - One More Time.mp3");
+        fs::write(&song, "audio").unwrap();
+        fs::write(song.with_extension("lrc"), "lyrics").unwrap();
+        fs::write(
+            root.join(".spotify-downloader-manifest.json"),
+            r#"{ "tracks": { "0DiWol3AO6WpXZgp0goxAV": "Road Trip/Daft Punk - One More Time.mp3", "4uLU6hMCjMI75M1A2tKUQC": "Justice - D.A.N.C.E..mp3" } }"#,
+        ).unwrap();
+
+        assert_eq!(delete_song(&root, &song), Ok(()));
+        assert!(!song.exists() && !song.with_extension("lrc").exists());
+        let manifest = fs::read_to_string(root.join(".spotify-downloader-manifest.json")).unwrap();
+        assert!(!manifest.contains("0DiWol3AO6WpXZgp0goxAV"));
+        assert!(manifest.contains("4uLU6hMCjMI75M1A2tKUQC"));
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn debug_args_are_allowlisted() {
+        let args = |args: &[&str]| debug_args(&args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>());
+        assert_eq!(args(&["--version"]), Ok(vec!["--version".to_string()]));
+        assert_eq!(
+            args(&["url", "https://open.spotify.com/track/4uLU6hMCjMI75M1A2tKUQC?si=x", "--log-level", "debug", "--audio", "youtube", "piped"]),
+            Ok(["url", "https://open.spotify.com/track/4uLU6hMCjMI75M1A2tKUQC", "--log-level", "DEBUG", "--audio", "youtube", "piped"]
+                .map(str::to_string)
+                .to_vec())
+        );
+        assert!(args(&[]).is_err());
+        assert!(args(&["download", "https://open.spotify.com/track/4uLU6hMCjMI75M1A2tKUQC"]).is_err());
+        assert!(args(&["url"]).is_err());
+        assert!(args(&["url", "https://open.spotify.com/track/4uLU6hMCjMI75M1A2tKUQC", "--output", "/etc"]).is_err());
+        assert!(args(&["save", "https://open.spotify.com/track/4uLU6hMCjMI75M1A2tKUQC", "--save-file", "/tmp/x"]).is_err());
+        assert!(args(&["url", "; rm -rf ~"]).is_err());
+        assert!(args(&["--version", "--help"]).is_err());
+    }
+
+    #[test]
+    fn missing_options_take_defaults() {
+        let options: DownloadOptions = serde_json::from_str(r#"{"threads": 2, "format": "flac"}"#).unwrap();
+        assert_eq!(options.threads, 2);
+        assert_eq!(options.format, "flac");
+        assert_eq!(options.overwrite, "skip");
+        assert!(options.embed_album_art);
+        assert!(!options.per_track);
+        assert!(options.delay_between_tracks_ms.is_none());
+        assert!(options.download_path.is_empty());
+    }
+
+    #[test]
+    fn ramps_threads_up_in_batches() {
+        assert_eq!(ramp_batches(30, 8), vec![(1, 2), (2, 4), (4, 8), (8, 16)]);
+        assert_eq!(ramp_batches(3, 8), vec![(1, 2), (2, 1)]);
+        assert_eq!(ramp_batches(20, 6), vec![(1, 2), (2, 4), (4, 8), (6, 6)]);
+        assert_eq!(ramp_batches(5, 1), vec![(1, 5)]);
+    }
+
+    #[test]
+    fn computes_speed() {
+        assert_eq!(compute_speed(0, 10.0), "calculating...");
+        assert_eq!(compute_speed(3, 0.0), "calculating...");
+        assert_eq!(compute_speed(10, 120.0), "5.0 songs/min");
+        assert_eq!(compute_speed(1, 90.0), "90s/song");
+        assert_eq!(songs_per_min(6, 60.0), Some(6.0));
+    }
+
+    #[test]
+    fn peak_speed_needs_a_full_window() {
+        let session = DownloadSession::new(false, "mp3");
+        let start = Instant::now();
+        let download_at = |secs: u64| {
+            session.downloaded.fetch_add(1, Ordering::SeqCst);
+            session.note_downloaded(start + Duration::from_secs(secs));
+        };
+
+        // Two quick songs are no window yet
+        download_at(0);
+        download_at(1);
+        assert_eq!(*lock_recovering(&session.peak_songs_per_min), None);
+
+        // 11 more songs in the 30s after the first one: 22 songs/min
+        (0..9).for_each(|i| download_at(2 + i * 3));
+        download_at(30);
+        assert_eq!(*lock_recovering(&session.peak_songs_per_min), Some(22.0));
+
+        // A slow stretch afterwards doesn't lower the peak
+        download_at(120);
+        assert_eq!(*lock_recovering(&session.peak_songs_per_min), Some(22.0));
+    }
+
+    #[test]
+    fn average_speed_leaves_out_skipped_songs() {
+        let session = DownloadSession::new(false, "mp3");
+        apply_line(&session, "INFO|Found 3 songs in Discovery (Album)");
+        apply_line(&session, "INFO|Skipping Daft Punk - Aerodynamic (file already exists) (duplicate)");
+        apply_line(&session, "INFO|Skipping Daft Punk - Digital Love (file already exists) (duplicate)");
+
+        let summary = session.summary_event();
+        assert_eq!(summary.average_songs_per_min, None);
+        assert_eq!(summary.peak_songs_per_min, None);
+    }
+
+    #[test]
+    fn progress_is_throttled() {
+        let session = DownloadSession::new(false, "mp3");
+        assert!(session.progress_due(false));
+        assert!(!session.progress_due(false));
+        // Phase changes are never held back
+        assert!(session.progress_due(true));
+
+        *session.last_progress_emit.lock().unwrap() = Some(Instant::now() - PROGRESS_INTERVAL);
+        assert!(session.progress_due(false));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn pid_is_stored_at_spawn() {
+        let state = DownloadState::default();
+        let mut child = spawn_tracked(Command::new("sleep").arg("5"), &state).expect("sleep should start");
+
+        // Cancel can reach spotdl before it prints anything
+        assert_ne!(state.child_pid.load(Ordering::SeqCst), 0);
+        assert_eq!(state.child_pid.load(Ordering::SeqCst), child.id());
+        let _ = child.kill();
+        let _ = child.wait();
+    }
+
+    fn apply_line(session: &DownloadSession, line: &str) -> ProgressUpdate {
+        let event = parser::parse_line(line).expect("line should parse");
+        apply_event(session, &event)
+    }
+
+    #[test]
+    fn found_line_sets_total() {
+        let session = DownloadSession::new(false, "mp3");
+        let progress = apply_line(&session, "INFO|Found 4 songs in Road Trip (Playlist)").progress.unwrap();
+
+        assert_eq!(progress.percent, 10);
+        assert_eq!(progress.total_tracks, 4);
+        assert_eq!(progress.current_track, 0);
+        assert_eq!(progress.message, "Found 4 song(s), starting download...");
+    }
+
+    #[test]
+    fn downloaded_and_skipped_lines_advance_progress() {
+        let session = DownloadSession::new(false, "mp3");
+        apply_line(&session, "INFO|Found 4 songs in Road Trip (Playlist)");
+
+        let update = apply_line(&session, "INFO|Downloaded \"Daft Punk - One More Time\": https://music.youtube.com/watch?v=abc");
+        let progress = update.progress.unwrap();
+        assert_eq!(progress.permille, 312);
+        assert_eq!(progress.percent, 31);
+        assert_eq!(progress.current_track, 1);
+        assert_eq!(progress.message, "Downloading...");
+        let track = update.track.unwrap();
+        assert_eq!((track.artist.as_str(), track.title.as_str()), ("Daft Punk", "One More Time"));
+        assert_eq!((track.index, track.total), (1, 4));
+
+        let update = apply_line(&session, "INFO|Skipping Daft Punk - Aerodynamic (file already exists) (duplicate)");
+        let progress = update.progress.unwrap();
+        assert_eq!(progress.percent, 52);
+        assert_eq!(progress.current_track, 2);
+        assert_eq!(progress.message, "Processing...");
+        assert!(matches!(update.track.unwrap().status, TrackStatus::Skipped));
+    }
+
+    #[test]
+    fn failed_line_reports_track_without_progress() {
+        let session = DownloadSession::new(false, "mp3");
+        apply_line(&session, "INFO|Found 2 songs in Discovery (Album)");
+
+        let update = apply_line(&session, "ERROR|LookupError: No results found for song: Daft Punk - Veridis Quo");
+        assert!(update.progress.is_none());
+        let track = update.track.unwrap();
+        assert!(matches!(track.status, TrackStatus::Failed));
+        assert_eq!(track.title, "Veridis Quo");
+        assert_eq!(track.index, 1);
+    }
+
+    #[test]
+    fn converting_line_reports_format() {
+        let session = DownloadSession::new(false, "flac");
+        let progress = apply_line(&session, "INFO|Converting \"Daft Punk - Digital Love\"").progress.unwrap();
+
+        assert_eq!(progress.percent, 90);
+        assert_eq!(progress.message, "Converting to FLAC...");
+        assert_eq!(progress.current_title.as_deref(), Some("Daft Punk - Digital Love"));
+    }
+
+    #[test]
+    fn failed_conversion_stops_counting() {
+        let session = DownloadSession::new(false, "mp3");
+        apply_line(&session, "INFO|Found 3 songs in Discovery (Album)");
+        let query = apply_line(&session, "INFO|Processing query: https://open.spotify.com/album/2noRn2Aes5aoNVsU6iWThc");
+        assert!(query.converting.is_none());
+
+        apply_line(&session, "INFO|Converting \"Daft Punk - One More Time\"");
+        apply_line(&session, "ERROR|FFmpegError: Failed to convert \"Daft Punk - One More Time\"");
+        assert_eq!(session.converting.load(Ordering::SeqCst), 0);
+
+        // Only the new conversion is in flight
+        let converting = apply_line(&session, "INFO|Converting \"Daft Punk - Aerodynamic\"").converting.unwrap();
+        assert_eq!(converting.index, 1);
+    }
+
+    #[test]
+    fn progress_survives_poisoned_counter() {
+        let session = DownloadSession::new(false, "mp3");
+        apply_line(&session, "INFO|Found 4 songs in Discovery (Album)");
+
+        // A reader thread panicking while it holds the lock poisons it
+        let _ = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let _guard = session.current_track.lock().unwrap();
+            panic!("reader thread died");
+        }));
+        assert!(session.current_track.is_poisoned());
+
+        let progress = apply_line(&session, "INFO|Downloaded \"Daft Punk - One More Time\": https://music.youtube.com/watch?v=abc")
+            .progress
+            .expect("progress should keep updating");
+        assert_eq!(progress.current_track, 1);
+        assert_eq!(progress.total_tracks, 4);
+    }
+
+    #[test]
+    fn percent_never_decreases() {
+        let session = DownloadSession::new(false, "mp3");
+        let lines = [
+            "INFO|Found 3 songs in Road Trip (Playlist)",
+            "INFO|Converting \"Daft Punk - One More Time\"",
+            "INFO|Downloaded \"Daft Punk - One More Time\": https://music.youtube.com/watch?v=a",
+            "INFO|Found 3 songs in Road Trip (Playlist)",
+            "INFO|Converting \"Daft Punk - Aerodynamic\"",
+            "[download]  45.3% of 5.21MiB at 1.50MiB/s ETA 00:03",
+            "INFO|Skipping Daft Punk - Aerodynamic (file already exists) (duplicate)",
+            "INFO|Downloaded \"Daft Punk - Digital Love\": https://music.youtube.com/watch?v=c",
+        ];
+
+        let permilles: Vec<u32> = lines
+            .iter()
+            .filter_map(|line| apply_line(&session, line).progress)
+            .map(|progress| progress.permille)
+            .collect();
+
+        assert_eq!(permilles.first(), Some(&100));
+        assert!(permilles.windows(2).all(|pair| pair[0] <= pair[1]), "progress went backwards: {:?}", permilles);
+        assert_eq!(permilles.last(), Some(&950));
+    }
+
+    #[test]
+    fn unrelated_lines_change_nothing() {
+        let session = DownloadSession::new(false, "mp3");
+        let update = apply_line(&session, "[youtube] abc: Downloading webpage");
+
+        assert!(update.progress.is_none());
+        assert!(update.track.is_none());
+    }
+}
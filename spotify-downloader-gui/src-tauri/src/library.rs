@@ -0,0 +1,280 @@
+// Songs already in a download folder, read from the tags embedded in their files.
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use lofty::file::{AudioFile, TaggedFileExt};
+use lofty::picture::{MimeType, PictureType};
+use lofty::tag::Accessor;
+use serde::Serialize;
+
+/// One audio file in the library view
+#[derive(Clone, Serialize)]
+pub struct DownloadedTrack {
+    pub path: String,
+    pub title: String,
+    pub artist: String,
+    pub album: Option<String>,
+    pub duration_sec: Option<u32>,
+}
+
+impl DownloadedTrack {
+    /// Read the file's ID3/Vorbis/MP4 tags. Files without readable tags are
+    /// described from their "Artist - Title" file name instead
+    fn read(path: &Path) -> Self {
+        let stem = path.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default();
+        let (fallback_artist, fallback_title) = match stem.split_once(" - ") {
+            Some((artist, title)) => (artist.trim().to_string(), title.trim().to_string()),
+            None => (String::new(), stem.trim().to_string()),
+        };
+
+        let tagged = lofty::read_from_path(path).ok();
+        let tag = tagged.as_ref().and_then(|file| file.primary_tag().or_else(|| file.first_tag()));
+        let text = |value: Option<std::borrow::Cow<'_, str>>| {
+            value.map(|v| v.trim().to_string()).filter(|v| !v.is_empty())
+        };
+
+        Self {
+            path: path.to_string_lossy().into_owned(),
+            title: tag.and_then(|tag| text(tag.title())).unwrap_or(fallback_title),
+            artist: tag.and_then(|tag| text(tag.artist())).unwrap_or(fallback_artist),
+            album: tag.and_then(|tag| text(tag.album())),
+            duration_sec: tagged
+                .as_ref()
+                .map(|file| file.properties().duration().as_secs() as u32)
+                .filter(|secs| *secs > 0),
+        }
+    }
+}
+
+/// Every audio file under `dir`, sorted by artist and then title
+pub fn list(dir: &Path) -> Vec<DownloadedTrack> {
+    let mut tracks: Vec<DownloadedTrack> = crate::recent_audio_files(dir, SystemTime::UNIX_EPOCH)
+        .iter()
+        .map(|file| DownloadedTrack::read(file))
+        .collect();
+    tracks.sort_by_cached_key(|track| (track.artist.to_lowercase(), track.title.to_lowercase()));
+    tracks
+}
+
+// Shorter songs are almost always cut off downloads
+const MIN_PLAUSIBLE_SECS: u32 = 5;
+
+/// An audio file that looks broken, with the search a re-download can use
+#[derive(Clone, Serialize)]
+pub struct IntegrityIssue {
+    pub path: String,
+    pub query: String,  // "Artist - Title"
+    pub problem: String,
+}
+
+/// Check every audio file under `dir` for being empty, unreadable or implausibly short.
+/// Files that pass go through `decode`, which returns the error a full decode hit, if any
+pub fn verify(dir: &Path, decode: impl Fn(&Path) -> Option<String>) -> Vec<IntegrityIssue> {
+    let mut files = crate::recent_audio_files(dir, SystemTime::UNIX_EPOCH);
+    files.sort();
+    files
+        .into_iter()
+        .filter_map(|file| {
+            let problem = if fs::metadata(&file).is_ok_and(|meta| meta.len() == 0) {
+                "The file is empty".to_string()
+            } else {
+                match lofty::read_from_path(&file) {
+                    Err(e) => format!("The file can't be read as audio: {}", e),
+                    Ok(tagged) => match tagged.properties().duration().as_secs() as u32 {
+                        secs if secs < MIN_PLAUSIBLE_SECS => format!("Only {}s long, the download was probably cut off", secs),
+                        _ => decode(&file)?,
+                    },
+                }
+            };
+
+            let track = DownloadedTrack::read(&file);
+            let query = match track.artist.is_empty() {
+                true => track.title,
+                false => format!("{} - {}", track.artist, track.title),
+            };
+            Some(IntegrityIssue { path: track.path, query, problem })
+        })
+        .collect()
+}
+
+/// Move the audio files under `from` into `to`, keeping their subfolders, and return how
+/// many were moved. `pattern` is a file name pattern where `*` and `?` are wildcards,
+/// matched ignoring case. Files whose name is already taken in `to` stay where they are,
+/// and a song's .lrc file goes along with it
+pub fn move_files(from: &Path, to: &Path, pattern: Option<&str>) -> Result<u32, String> {
+    let mut moved = 0;
+    for file in crate::recent_audio_files(from, SystemTime::UNIX_EPOCH) {
+        let name = file.file_name().map(|name| name.to_string_lossy().to_lowercase()).unwrap_or_default();
+        if pattern.is_some_and(|pattern| !matches_pattern(&name, &pattern.to_lowercase())) {
+            continue;
+        }
+
+        let dest = to.join(file.strip_prefix(from).unwrap_or(&file));
+        if dest.exists() {
+            continue;
+        }
+        if let Some(dir) = dest.parent() {
+            fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        }
+        move_file(&file, &dest).map_err(|e| format!("Failed to move {}: {}", file.display(), e))?;
+        moved += 1;
+
+        let lyrics = file.with_extension("lrc");
+        let lyrics_dest = dest.with_extension("lrc");
+        if lyrics.exists() && !lyrics_dest.exists() {
+            let _ = move_file(&lyrics, &lyrics_dest);
+        }
+    }
+    Ok(moved)
+}
+
+/// Rename a file, or copy and delete it when `dest` is on another filesystem
+fn move_file(file: &Path, dest: &Path) -> io::Result<()> {
+    if fs::rename(file, dest).is_ok() {
+        return Ok(());
+    }
+
+    // create_new, so a file that appeared at `dest` in the meantime is never overwritten
+    let mut source = fs::File::open(file)?;
+    let mut target = fs::OpenOptions::new().write(true).create_new(true).open(dest)?;
+    let copied = io::copy(&mut source, &mut target).and_then(|_| target.sync_all());
+    drop(target);
+    if let Err(e) = copied.and_then(|_| fs::remove_file(file)) {
+        // Keep the original, not a second copy
+        let _ = fs::remove_file(dest);
+        return Err(e);
+    }
+    Ok(())
+}
+
+/// Whether `name` matches `pattern`, where `*` stands for any run of characters and `?` for one
+fn matches_pattern(name: &str, pattern: &str) -> bool {
+    let name: Vec<char> = name.chars().collect();
+    let pattern: Vec<char> = pattern.chars().collect();
+    let (mut n, mut p) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;  // where the last `*` was, and where its match ends
+
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                n += 1;
+                p += 1;
+            }
+            _ => match star {
+                // Let the last `*` take one more character and try again
+                Some((star_p, star_n)) => {
+                    star = Some((star_p, star_n + 1));
+                    p = star_p + 1;
+                    n = star_n + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// The embedded front cover of an audio file (any picture when none is marked as
+/// the front), with the file extension its image format uses
+fn embedded_cover(file: &Path) -> Option<(Vec<u8>, &'static str)> {
+    let tagged = lofty::read_from_path(file).ok()?;
+    let tag = tagged.primary_tag().or_else(|| tagged.first_tag())?;
+    let pictures = tag.pictures();
+    let picture = pictures.iter()
+        .find(|picture| picture.pic_type() == PictureType::CoverFront)
+        .or_else(|| pictures.first())?;
+    if picture.data().is_empty() {
+        return None;
+    }
+    let extension = match picture.mime_type() {
+        Some(MimeType::Png) => "png",
+        _ => "jpg",
+    };
+    Some((picture.data().to_vec(), extension))
+}
+
+/// Write a cover.jpg (or cover.png) next to the songs downloaded since `since`, one per
+/// folder, taken from the art embedded in the first song that has some. Folders that
+/// already have a cover are left alone. Returns the folders whose songs carry no art,
+/// e.g. because the format can't embed it
+pub fn save_folder_covers(dir: &Path, since: SystemTime) -> Vec<PathBuf> {
+    let mut folders: BTreeMap<PathBuf, Vec<PathBuf>> = BTreeMap::new();
+    for file in crate::recent_audio_files(dir, since) {
+        let folder = file.parent().map_or_else(|| dir.to_path_buf(), Path::to_path_buf);
+        folders.entry(folder).or_default().push(file);
+    }
+
+    let mut without_art = Vec::new();
+    for (folder, mut files) in folders {
+        if ["cover.jpg", "cover.png"].iter().any(|name| folder.join(name).exists()) {
+            continue;
+        }
+
+        files.sort();
+        match files.iter().find_map(|file| embedded_cover(file)) {
+            Some((data, extension)) => {
+                let _ = fs::write(folder.join(format!("cover.{}", extension)), data);
+            }
+            None => without_art.push(folder),
+        }
+    }
+    without_art
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A fresh folder under the system temp directory, per test and per run
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("spotify-downloader-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn moves_downloads_without_clobbering() {
+        let root = temp_dir("move");
+        let (staging, music) = (root.join("staging"), root.join("music"));
+        fs::create_dir_all(staging.join("Road Trip")).unwrap();
+        fs::create_dir_all(&music).unwrap();
+        fs::write(staging.join("Road Trip/Daft Punk - One More Time.mp3"), "new").unwrap();
+        fs::write(staging.join("Road Trip/Daft Punk - One More Time.lrc"), "lyrics").unwrap();
+        fs::write(staging.join("Justice - D.A.N.C.E..mp3"), "new").unwrap();
+        fs::write(staging.join("Daft Punk - Voyager.flac"), "new").unwrap();
+        fs::write(music.join("Daft Punk - Voyager.flac"), "old").unwrap();
+
+        assert_eq!(move_files(&staging, &music, Some("daft punk*")), Ok(1));
+        assert_eq!(fs::read_to_string(music.join("Road Trip/Daft Punk - One More Time.mp3")).unwrap(), "new");
+        assert!(music.join("Road Trip/Daft Punk - One More Time.lrc").exists());
+        // The library's copy is kept and the new one stays behind
+        assert_eq!(fs::read_to_string(music.join("Daft Punk - Voyager.flac")).unwrap(), "old");
+        assert!(staging.join("Daft Punk - Voyager.flac").exists());
+        assert!(staging.join("Justice - D.A.N.C.E..mp3").exists());
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn flags_broken_downloads() {
+        let root = temp_dir("verify");
+        fs::create_dir_all(root.join("Road Trip")).unwrap();
+        fs::write(root.join("Daft Punk - One More Time.mp3"), "").unwrap();
+        fs::write(root.join("Road Trip/Justice - D.A.N.C.E..flac"), "not audio").unwrap();
+
+        let issues = verify(&root, |_| None);
+        let problems: Vec<(&str, &str)> = issues.iter().map(|issue| (issue.query.as_str(), issue.problem.as_str())).collect();
+        assert_eq!(problems.len(), 2);
+        assert_eq!(problems[0], ("Daft Punk - One More Time", "The file is empty"));
+        assert!(problems[1].0 == "Justice - D.A.N.C.E." && problems[1].1.starts_with("The file can't be read as audio"));
+
+        let _ = fs::remove_dir_all(&root);
+    }
+}
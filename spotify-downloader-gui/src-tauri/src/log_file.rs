@@ -0,0 +1,63 @@
+// Per-download log files in the app log directory, only the newest few are kept.
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+
+// Old logs beyond this count are deleted when a new one is created
+const MAX_LOG_FILES: usize = 20;
+const LOG_PREFIX: &str = "download-";
+
+/// Raw spotdl output of one download, for attaching to bug reports
+pub struct LogFile {
+    path: PathBuf,
+    file: Mutex<File>,
+}
+
+impl LogFile {
+    /// Start a timestamped log file, pruning the oldest ones to stay under MAX_LOG_FILES
+    pub fn create(app: &AppHandle) -> Result<Self, String> {
+        let dir = app.path().app_log_dir()
+            .map_err(|e| format!("Failed to resolve log directory: {}", e))?;
+        fs::create_dir_all(&dir).map_err(|e| format!("Failed to create log directory: {}", e))?;
+        prune(&dir, MAX_LOG_FILES - 1);
+
+        let name = format!("{}{}.log", LOG_PREFIX, chrono::Local::now().format("%Y%m%d-%H%M%S-%3f"));
+        let path = dir.join(name);
+        let file = File::create(&path).map_err(|e| format!("Failed to create log file: {}", e))?;
+        Ok(Self { path, file: Mutex::new(file) })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append one line, tagged with the stream it came from
+    pub fn write_line(&self, stream: &str, line: &str) {
+        if let Ok(mut file) = self.file.lock() {
+            let _ = writeln!(file, "[{}] {}", stream, line);
+        }
+    }
+}
+
+/// Delete the oldest download logs so at most `keep` remain
+fn prune(dir: &Path, keep: usize) {
+    let Ok(entries) = fs::read_dir(dir) else { return };
+    let mut logs: Vec<PathBuf> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with(LOG_PREFIX) && name.ends_with(".log"))
+        })
+        .collect();
+
+    // Timestamped names sort oldest first
+    logs.sort();
+    let excess = logs.len().saturating_sub(keep);
+    for path in &logs[..excess] {
+        let _ = fs::remove_file(path);
+    }
+}
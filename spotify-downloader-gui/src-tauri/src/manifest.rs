@@ -0,0 +1,133 @@
+// Per-folder record of the Spotify songs already downloaded there, so a repeat
+// download of a playlist only hands spotdl the songs that are new.
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+use serde::{Deserialize, Serialize};
+use crate::metadata::{self, SongMetadata};
+
+const MANIFEST_FILE: &str = ".spotify-downloader-manifest.json";
+
+// Finished songs are matched to their files and written out at most this often
+const SAVE_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Manifest {
+    tracks: BTreeMap<String, PathBuf>,  // Spotify track id -> file, relative to the folder
+}
+
+impl Manifest {
+    /// Read the folder's manifest, empty when there is none or it can't be parsed
+    pub fn load(dir: &Path) -> Self {
+        fs::read_to_string(dir.join(MANIFEST_FILE))
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    /// Whether the song was downloaded here and its file hasn't been deleted since
+    pub fn has(&self, dir: &Path, song: &SongMetadata) -> bool {
+        track_id(&song.url)
+            .and_then(|id| self.tracks.get(id))
+            .is_some_and(|file| dir.join(file).is_file())
+    }
+
+    /// Drop the song whose file is `file` from the manifest in `dir`, if there is one that
+    /// lists it, so the next download fetches the song again
+    pub fn forget_file(dir: &Path, file: &Path) -> Result<(), String> {
+        if !dir.join(MANIFEST_FILE).is_file() {
+            return Ok(());
+        }
+        let mut manifest = Self::load(dir);
+        let before = manifest.tracks.len();
+        manifest.tracks.retain(|_, listed| dir.join(listed) != file);
+        if manifest.tracks.len() == before {
+            return Ok(());
+        }
+        manifest.save(dir)
+    }
+
+    fn save(&self, dir: &Path) -> Result<(), String> {
+        let contents = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize manifest: {}", e))?;
+        fs::write(dir.join(MANIFEST_FILE), contents).map_err(|e| format!("Failed to write manifest: {}", e))
+    }
+}
+
+/// The id in an open.spotify.com/track/<id> link
+fn track_id(url: &str) -> Option<&str> {
+    let id = url.split_once("/track/")?.1;
+    let id = id.split(['?', '/']).next().unwrap_or(id);
+    Some(id).filter(|id| !id.is_empty())
+}
+
+/// Adds songs to a folder's manifest as spotdl reports them downloaded
+pub struct Recorder {
+    dir: PathBuf,
+    manifest: Manifest,
+    ids: HashMap<String, String>,  // normalized "Artist - Title" -> track id
+    since: SystemTime,
+    pending: Vec<(String, String)>,  // (normalized name, track id) of songs not matched to a file yet
+    last_saved: Instant,
+}
+
+impl Recorder {
+    pub fn new(dir: &Path, manifest: Manifest, songs: &[SongMetadata], since: SystemTime) -> Self {
+        let ids = songs
+            .iter()
+            .filter_map(|song| Some((metadata::normalize_name(&song.display_name()), track_id(&song.url)?.to_string())))
+            .collect();
+        Self { dir: dir.to_path_buf(), manifest, ids, since, pending: Vec::new(), last_saved: Instant::now() }
+    }
+
+    /// Remember a song spotdl downloaded, or skipped because it was already there.
+    /// Its file is looked up and the manifest written once SAVE_INTERVAL has passed,
+    /// and when the recorder is dropped, so a cancelled download keeps its songs
+    pub fn record(&mut self, track: &str) {
+        let name = metadata::normalize_name(track);
+        let Some(id) = self.ids.get(&name) else { return };
+        self.pending.push((name, id.clone()));
+        if self.last_saved.elapsed() >= SAVE_INTERVAL {
+            self.save_pending();
+        }
+    }
+
+    /// Match the pending songs to their files with one scan of the folder and write the manifest
+    fn save_pending(&mut self) {
+        self.last_saved = Instant::now();
+        if self.pending.is_empty() {
+            return;
+        }
+
+        let named = |files: Vec<PathBuf>| -> Vec<(String, PathBuf)> {
+            files
+                .into_iter()
+                .filter_map(|file| Some((metadata::normalize_name(&file.file_stem()?.to_string_lossy()), file)))
+                .collect()
+        };
+        let recent = named(crate::recent_audio_files(&self.dir, self.since));
+        let mut existing = None;  // files from before the download, listed on the first skip
+        for (name, id) in std::mem::take(&mut self.pending) {
+            let find = |files: &[(String, PathBuf)]| files.iter().find(|(stem, _)| *stem == name).map(|(_, file)| file.clone());
+            let file = match find(&recent) {
+                Some(file) => file,
+                None => {
+                    let existing = existing.get_or_insert_with(|| named(crate::recent_audio_files(&self.dir, SystemTime::UNIX_EPOCH)));
+                    let Some(file) = find(existing) else { continue };
+                    file
+                }
+            };
+            let relative = file.strip_prefix(&self.dir).map(Path::to_path_buf).unwrap_or(file);
+            self.manifest.tracks.insert(id, relative);
+        }
+        let _ = self.manifest.save(&self.dir);
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        self.save_pending();
+    }
+}
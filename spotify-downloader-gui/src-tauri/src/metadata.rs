@@ -0,0 +1,113 @@
+// Track metadata resolved by spotdl without downloading any audio.
+use std::fs;
+use std::process::{Command, ExitStatus, Stdio};
+use std::time::{SystemTime, UNIX_EPOCH};
+use serde::{Deserialize, Serialize};
+
+/// One song as written by `spotdl save`; missing fields fall back to defaults
+#[derive(Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct SongMetadata {
+    pub name: String,
+    pub artists: Vec<String>,
+    pub url: String,
+    pub duration: u32,  // seconds
+    pub album_name: String,
+    pub list_name: Option<String>,  // the playlist, album or artist the song was listed under
+}
+
+impl SongMetadata {
+    /// "Artist, Artist - Title", the file stem spotdl's default output template produces
+    pub fn display_name(&self) -> String {
+        format!("{} - {}", self.artists.join(", "), self.name)
+    }
+}
+
+/// A song as shown in the download preview
+#[derive(Clone, Serialize)]
+pub struct TrackInfo {
+    pub title: String,
+    pub artist: String,
+    pub duration_sec: u32,
+}
+
+impl From<SongMetadata> for TrackInfo {
+    fn from(song: SongMetadata) -> Self {
+        Self {
+            artist: song.artists.join(", "),
+            title: song.name,
+            duration_sec: song.duration,
+        }
+    }
+}
+
+/// Resolve the songs behind a Spotify URL with `spotdl save`, `extra_args` go before the URL.
+/// `spotdl` is the bare spotdl command, with its environment already set up
+pub fn fetch_songs(spotdl: Command, url: &str, extra_args: &[String]) -> Result<Vec<SongMetadata>, String> {
+    fetch_songs_with(spotdl, url, extra_args, |cmd| {
+        cmd.output().map(|output| output.status).map_err(|e| format!("Failed to run spotdl: {}", e))
+    })
+}
+
+/// fetch_songs with `run` starting spotdl and waiting for it, so a download can cancel the lookup
+pub fn fetch_songs_with(
+    mut spotdl: Command,
+    url: &str,
+    extra_args: &[String],
+    run: impl FnOnce(&mut Command) -> Result<ExitStatus, String>,
+) -> Result<Vec<SongMetadata>, String> {
+    let stamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis()).unwrap_or(0);
+    let save_file = std::env::temp_dir().join(format!("spotify-downloader-{}.spotdl", stamp));
+
+    spotdl
+        .arg("save")
+        .args(extra_args)
+        .arg(url)
+        .arg("--save-file")
+        .arg(&save_file)
+        .stdin(Stdio::null());
+    let status = run(&mut spotdl)?;
+
+    let contents = fs::read_to_string(&save_file);
+    let _ = fs::remove_file(&save_file);
+
+    if !status.success() {
+        return Err("spotdl could not resolve the tracks for this URL".to_string());
+    }
+    let contents = contents.map_err(|e| format!("Failed to read track list: {}", e))?;
+    serde_json::from_str(&contents).map_err(|e| format!("Failed to parse track list: {}", e))
+}
+
+// Names Windows reserves for devices, in any case and with any extension
+const RESERVED_NAMES: &[&str] = &[
+    "con", "prn", "aux", "nul",
+    "com1", "com2", "com3", "com4", "com5", "com6", "com7", "com8", "com9",
+    "lpt1", "lpt2", "lpt3", "lpt4", "lpt5", "lpt6", "lpt7", "lpt8", "lpt9",
+];
+
+/// A playlist or album name made safe to use as a folder name on any OS:
+/// characters Windows forbids become "_" and trailing dots and spaces are dropped
+pub fn folder_name(name: &str) -> String {
+    let cleaned: String = name
+        .chars()
+        .map(|c| if c.is_control() || matches!(c, '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*') { '_' } else { c })
+        .collect();
+    let cleaned = cleaned.trim().trim_end_matches(['.', ' ']).to_string();
+
+    let stem = cleaned.split('.').next().unwrap_or("").to_lowercase();
+    if cleaned.is_empty() {
+        "Playlist".to_string()
+    } else if RESERVED_NAMES.contains(&stem.as_str()) {
+        format!("_{}", cleaned)
+    } else {
+        cleaned
+    }
+}
+
+/// Lowercased alphanumerics only, so sanitized filenames still match their song
+pub fn normalize_name(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
@@ -0,0 +1,482 @@
+// Parser for spotdl's log output.
+//
+// spotdl is started with `--log-format "%(levelname)s|%(message)s"`, so every
+// log record arrives as `LEVEL|message`. The level prefix is matched exactly and
+// the message is then classified into a SpotdlEvent, so the progress code never
+// has to look at raw output lines itself.
+
+use serde::Serialize;
+
+// Passed to spotdl's --log-format
+pub const LOG_FORMAT: &str = "%(levelname)s|%(message)s";
+
+// Match scores below this (out of 100) are worth checking by ear
+const LOW_MATCH_SCORE: f64 = 70.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum LogLevel {
+    Debug,
+    Info,
+    Warning,
+    Error,
+    // Lines without a level prefix, e.g. from yt-dlp or ffmpeg
+    Unknown,
+}
+
+impl LogLevel {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "DEBUG" => Some(Self::Debug),
+            "INFO" => Some(Self::Info),
+            "WARNING" => Some(Self::Warning),
+            "ERROR" | "CRITICAL" => Some(Self::Error),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SpotdlEvent {
+    Found { count: u32 },
+    Downloaded { track: String },
+    Skipped { track: String },
+    MetadataUpdated { track: String },
+    Removed { file: String },  // `spotdl sync` deleting a song that left the playlist
+    Failed { track: String, region_locked: bool },
+    Converting { track: Option<String> },
+    MissingLyrics { track: String },
+    LowConfidenceMatch { track: String, score: Option<f64> },  // the audio may be the wrong song
+    FfmpegMissing,
+    Transfer { bytes_per_sec: u64 },
+    Other,
+}
+
+/// Rough importance of a log line, for the download log the UI shows
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+    Info,
+}
+
+/// Why a download failed, so the UI can offer the right way out
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    InvalidUrl,
+    Auth,  // credentials refused, or rate-limited by Spotify
+    NoMatch,
+    Network,
+    Ffmpeg,
+    Disk,
+}
+
+impl ErrorKind {
+    /// What went wrong and what to do about it, for the final error message
+    pub fn message(self) -> &'static str {
+        match self {
+            Self::InvalidUrl => "Spotify doesn't know this link. Check that it is complete and still exists.",
+            Self::Auth => "Spotify refused the request or is rate limiting it. Wait a while or add your own API credentials.",
+            Self::NoMatch => "No matching audio was found for the songs.",
+            Self::Network => "A network error interrupted the download. Check the connection and try again.",
+            Self::Ffmpeg => "ffmpeg failed to convert the songs.",
+            Self::Disk => "The songs could not be written. Check the folder's free space and permissions.",
+        }
+    }
+}
+
+/// The kind of problem an error line reports, None when it doesn't match a known one
+pub fn classify_error(line: &str) -> Option<ErrorKind> {
+    let lower = split_level(line.trim()).1.to_lowercase();
+    let has = |patterns: &[&str]| patterns.iter().any(|pattern| lower.contains(pattern));
+
+    if lower.contains("ffmpeg") {
+        Some(ErrorKind::Ffmpeg)
+    } else if has(&["no space left", "errno 28", "disk full", "permission denied", "read-only file system"]) {
+        Some(ErrorKind::Disk)
+    } else if has(&["rate limit", "rate/request limit", "too many requests", "status: 429", "status: 401", "invalid_client", "invalid client", "unauthorized", "access token"]) {
+        Some(ErrorKind::Auth)
+    } else if has(&["invalid url", "invalid id", "invalid base62", "non existing id", "queryerror", "invalid query"]) {
+        Some(ErrorKind::InvalidUrl)
+    } else if has(&["connectionerror", "connection reset", "connection refused", "connection aborted", "timed out", "max retries exceeded", "name resolution", "getaddrinfo", "network is unreachable", "sslerror"]) {
+        Some(ErrorKind::Network)
+    } else if has(&["no results found", "lookuperror", "no match", "could not match"]) {
+        Some(ErrorKind::NoMatch)
+    } else {
+        None
+    }
+}
+
+/// Whether a line reports a problem that may go away on its own, like a dropped connection,
+/// rate limiting or a server error, so the song is worth another try
+pub fn is_transient_error(line: &str) -> bool {
+    let lower = split_level(line.trim()).1.to_lowercase();
+    classify_error(line) == Some(ErrorKind::Network)
+        || ["rate limit", "rate/request limit", "too many requests", "status: 429", "status: 500", "status: 502", "status: 503", "status: 504", "service unavailable", "temporarily unavailable"]
+            .iter()
+            .any(|pattern| lower.contains(pattern))
+}
+
+/// Severity of a raw output line and its message without the level prefix.
+/// Lines without a prefix are guessed from keywords.
+pub fn log_severity(line: &str) -> (Severity, &str) {
+    let (level, message) = split_level(line.trim());
+    let severity = match level {
+        LogLevel::Error => Severity::Error,
+        LogLevel::Warning => Severity::Warning,
+        LogLevel::Debug | LogLevel::Info => Severity::Info,
+        LogLevel::Unknown => {
+            let lower = message.to_lowercase();
+            if lower.contains("error") || lower.contains("exception") || lower.contains("traceback") || lower.contains("failed") {
+                Severity::Error
+            } else if lower.contains("warning") || lower.contains("warn") {
+                Severity::Warning
+            } else {
+                Severity::Info
+            }
+        }
+    };
+    (severity, message)
+}
+
+/// Classify one raw output line, `None` for blank lines
+pub fn parse_line(line: &str) -> Option<SpotdlEvent> {
+    let line = line.trim();
+    if line.is_empty() {
+        return None;
+    }
+
+    let (level, message) = split_level(line);
+    Some(classify(level, message))
+}
+
+fn split_level(line: &str) -> (LogLevel, &str) {
+    line.split_once('|')
+        .and_then(|(prefix, message)| Some((LogLevel::from_name(prefix.trim())?, message.trim())))
+        .unwrap_or((LogLevel::Unknown, line))
+}
+
+fn classify(level: LogLevel, message: &str) -> SpotdlEvent {
+    let lower = message.to_lowercase();
+
+    if is_ffmpeg_missing(&lower) {
+        return SpotdlEvent::FfmpegMissing;
+    }
+
+    if lower.contains("lyrics") && (lower.contains("no ") || lower.contains("not found") || lower.contains("failed")) {
+        if let Some(track) = extract_quoted(message) {
+            return SpotdlEvent::MissingLyrics { track: track.to_string() };
+        }
+    }
+
+    // Checked before failures, a best guess after "no results found" still got downloaded
+    if let Some(score) = low_confidence_score(&lower) {
+        return SpotdlEvent::LowConfidenceMatch { track: extract_track_name(message).to_string(), score };
+    }
+
+    // Errors about a named song are failures even when the wording is new to us
+    if is_track_failure(&lower) || (level == LogLevel::Error && extract_quoted(message).is_some()) {
+        return SpotdlEvent::Failed {
+            track: extract_track_name(message).to_string(),
+            region_locked: is_region_error(&lower),
+        };
+    }
+
+    if (message.contains("Found") && message.contains("song")) || message.contains("Processing query") {
+        if let Some(count) = extract_song_count(message) {
+            return SpotdlEvent::Found { count };
+        }
+    }
+
+    if message.contains("Downloaded") {
+        let track = extract_quoted(message).unwrap_or_default();
+        return SpotdlEvent::Downloaded { track: track.to_string() };
+    }
+
+    // --overwrite metadata retags existing files instead of downloading them
+    if let Some(rest) = message.strip_prefix("Updating metadata for ") {
+        let track = extract_quoted(rest).unwrap_or(rest.trim());
+        return SpotdlEvent::MetadataUpdated { track: track.to_string() };
+    }
+
+    if let Some(file) = message.strip_prefix("Deleting ").or_else(|| message.strip_prefix("Removing ")) {
+        let file = extract_quoted(file).unwrap_or(file.trim());
+        return SpotdlEvent::Removed { file: file.to_string() };
+    }
+
+    if message.contains("Skipping") {
+        let track = extract_skipped_track(message).unwrap_or_default();
+        return SpotdlEvent::Skipped { track: track.to_string() };
+    }
+
+    // Only the conversion itself, spotdl's "Processing query" lines come long before it
+    if message.starts_with("Converting") {
+        return SpotdlEvent::Converting { track: extract_quoted(message).map(str::to_string) };
+    }
+
+    if let Some(bytes_per_sec) = parse_transfer_rate(message) {
+        return SpotdlEvent::Transfer { bytes_per_sec };
+    }
+
+    SpotdlEvent::Other
+}
+
+/// Whether a lowercased output line says ffmpeg can't be found or run
+fn is_ffmpeg_missing(lower: &str) -> bool {
+    lower.contains("ffmpeg")
+        && (lower.contains("not installed")
+            || lower.contains("not found")
+            || lower.contains("no such file")
+            || lower.contains("is not recognized"))
+}
+
+/// Some(score) when a lowercased output line says a song was matched with low confidence,
+/// the score being None when the line only says so in words
+fn low_confidence_score(lower: &str) -> Option<Option<f64>> {
+    let worded = ["best guess", "low confidence", "low match", "closest match", "poor match", "may be incorrect"]
+        .iter()
+        .any(|pattern| lower.contains(pattern));
+    let score = lower
+        .split_once("score")
+        .and_then(|(_, rest)| rest.split_whitespace().find_map(|word| word.trim_matches(|c: char| !c.is_ascii_digit() && c != '.').parse::<f64>().ok()));
+
+    match score {
+        Some(score) if score < LOW_MATCH_SCORE => Some(Some(score)),
+        _ if worded => Some(score),
+        _ => None,
+    }
+}
+
+/// Whether a lowercased output line reports a track as blocked in this region
+fn is_region_error(lower: &str) -> bool {
+    lower.contains("not available in your country")
+        || lower.contains("not available in your region")
+        || lower.contains("geo-restricted")
+        || lower.contains("geo restricted")
+        || (lower.contains("region") && (lower.contains("restrict") || lower.contains("lock") || lower.contains("block")))
+}
+
+/// Whether a lowercased output line reports that a track could not be downloaded
+fn is_track_failure(lower: &str) -> bool {
+    lower.contains("lookuperror")
+        || lower.contains("no results found")
+        || lower.contains("failed to download")
+        || lower.contains("audioprovidererror")
+        || lower.contains("downloaderror")
+        || is_region_error(lower)
+}
+
+/// Best-effort track name from an error line: the quoted part, else the text after the last colon
+fn extract_track_name(line: &str) -> &str {
+    extract_quoted(line)
+        .or_else(|| line.rsplit_once(": ").map(|(_, name)| name.trim()))
+        .unwrap_or(line)
+}
+
+/// Song label from a skip line: `Skipping Artist - Title (file already exists) (duplicate)`
+fn extract_skipped_track(line: &str) -> Option<&str> {
+    if let Some(quoted) = extract_quoted(line) {
+        return Some(quoted);
+    }
+
+    let rest = line.split_once("Skipping ")?.1;
+    let label = rest.split(" (").next().unwrap_or(rest).trim();
+    Some(label).filter(|label| !label.is_empty())
+}
+
+/// Get the first double-quoted part of a line, e.g. a song name spotdl prints
+fn extract_quoted(s: &str) -> Option<&str> {
+    let start = s.find('"')? + 1;
+    let len = s[start..].find('"')?;
+    Some(&s[start..start + len]).filter(|quoted| !quoted.is_empty())
+}
+
+/// A transfer rate like yt-dlp's "at 1.23MiB/s" or "3.2 MB/s", in bytes per second
+fn parse_transfer_rate(message: &str) -> Option<u64> {
+    let words: Vec<&str> = message.split_whitespace().collect();
+    words.iter().enumerate().find_map(|(i, word)| {
+        let rate = word.strip_suffix("/s")?;
+        let split = rate.find(|c: char| c.is_ascii_alphabetic())?;
+        let (number, unit) = rate.split_at(split);
+        let number = if number.is_empty() { *words.get(i.checked_sub(1)?)? } else { number };
+
+        let multiplier: f64 = match unit {
+            "B" => 1.0,
+            "KiB" => 1024.0,
+            "MiB" => 1024.0 * 1024.0,
+            "GiB" => 1024.0 * 1024.0 * 1024.0,
+            "kB" | "KB" => 1000.0,
+            "MB" => 1000.0 * 1000.0,
+            "GB" => 1000.0 * 1000.0 * 1000.0,
+            _ => return None,
+        };
+        let value: f64 = number.parse().ok()?;
+        Some((value * multiplier).round() as u64)
+    })
+}
+
+/// The count right before "song", "songs" or "song(s)", as in spotdl's "Found 12 songs in
+/// <list>". Other numbers, like ones in a playlist's name, are ignored
+fn extract_song_count(s: &str) -> Option<u32> {
+    let words: Vec<&str> = s.split_whitespace().collect();
+    words.windows(2).find_map(|pair| {
+        let noun = pair[1].to_lowercase();
+        let noun = noun.trim_end_matches([',', '.', ':', ';']);
+        if !matches!(noun, "song" | "songs" | "song(s)") {
+            return None;
+        }
+        pair[0].replace(',', "").parse().ok()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(line: &str) -> SpotdlEvent {
+        parse_line(line).expect("line should parse")
+    }
+
+    #[test]
+    fn splits_log_level_prefix() {
+        assert_eq!(split_level("WARNING|Something odd happened"), (LogLevel::Warning, "Something odd happened"));
+        assert_eq!(split_level("[youtube] Extracting URL"), (LogLevel::Unknown, "[youtube] Extracting URL"));
+    }
+
+    #[test]
+    fn ignores_blank_lines() {
+        assert!(parse_line("   ").is_none());
+    }
+
+    #[test]
+    fn classifies_progress_lines() {
+        assert_eq!(event("INFO|Found 12 songs in Road Trip (Playlist)"), SpotdlEvent::Found { count: 12 });
+        assert_eq!(
+            event("INFO|Downloaded \"Daft Punk - One More Time\": https://music.youtube.com/watch?v=abc"),
+            SpotdlEvent::Downloaded { track: "Daft Punk - One More Time".to_string() }
+        );
+        assert_eq!(
+            event("INFO|Skipping Daft Punk - Aerodynamic (file already exists) (duplicate)"),
+            SpotdlEvent::Skipped { track: "Daft Punk - Aerodynamic".to_string() }
+        );
+        assert_eq!(
+            event("INFO|Updating metadata for Daft Punk - Aerodynamic"),
+            SpotdlEvent::MetadataUpdated { track: "Daft Punk - Aerodynamic".to_string() }
+        );
+        assert_eq!(
+            event("INFO|Converting \"Daft Punk - Digital Love\""),
+            SpotdlEvent::Converting { track: Some("Daft Punk - Digital Love".to_string()) }
+        );
+        assert_eq!(
+            event("INFO|Deleting Daft Punk - Face to Face.mp3"),
+            SpotdlEvent::Removed { file: "Daft Punk - Face to Face.mp3".to_string() }
+        );
+        // Forced overwrites are followed by a regular "Downloaded" line
+        assert_eq!(event("INFO|Overwriting Daft Punk - Aerodynamic"), SpotdlEvent::Other);
+    }
+
+    #[test]
+    fn parses_transfer_rates() {
+        assert_eq!(
+            event("[download]  45.3% of 5.21MiB at 1.50MiB/s ETA 00:03"),
+            SpotdlEvent::Transfer { bytes_per_sec: 1_572_864 }
+        );
+        assert_eq!(event("INFO|Downloading at 3.2 MB/s"), SpotdlEvent::Transfer { bytes_per_sec: 3_200_000 });
+        assert_eq!(event("INFO|Speed: 4 songs/s"), SpotdlEvent::Other);
+    }
+
+    #[test]
+    fn classifies_failures() {
+        assert_eq!(
+            event("ERROR|LookupError: No results found for song: Daft Punk - Veridis Quo"),
+            SpotdlEvent::Failed { track: "Daft Punk - Veridis Quo".to_string(), region_locked: false }
+        );
+        assert_eq!(
+            event("ERROR|\"Daft Punk - Crescendolls\" is not available in your country"),
+            SpotdlEvent::Failed { track: "Daft Punk - Crescendolls".to_string(), region_locked: true }
+        );
+        // Unfamiliar wording still counts when an error names a song
+        assert_eq!(
+            event("ERROR|Something new went wrong with \"Daft Punk - Nightvision\""),
+            SpotdlEvent::Failed { track: "Daft Punk - Nightvision".to_string(), region_locked: false }
+        );
+    }
+
+    #[test]
+    fn flags_low_confidence_matches() {
+        assert_eq!(
+            event("WARNING|No results found for \"Daft Punk - Veridis Quo\", using best guess"),
+            SpotdlEvent::LowConfidenceMatch { track: "Daft Punk - Veridis Quo".to_string(), score: None }
+        );
+        assert_eq!(
+            event("WARNING|Low match score 54.2 for \"Daft Punk - Something About Us\""),
+            SpotdlEvent::LowConfidenceMatch { track: "Daft Punk - Something About Us".to_string(), score: Some(54.2) }
+        );
+        // A good score is nothing to warn about
+        assert_eq!(event("INFO|Match score 91.5 for \"Daft Punk - Voyager\""), SpotdlEvent::Other);
+    }
+
+    #[test]
+    fn classifies_error_kinds() {
+        let kind = |line| classify_error(line);
+        assert_eq!(kind("ERROR|spotipy.exceptions.SpotifyException: http status: 400, code:-1 - invalid id"), Some(ErrorKind::InvalidUrl));
+        assert_eq!(kind("ERROR|http status: 429, code:-1 - Max Retries, reason: too many 429 error responses"), Some(ErrorKind::Auth));
+        assert_eq!(kind("ERROR|SpotifyOauthError: error: invalid_client, error_description: Invalid client"), Some(ErrorKind::Auth));
+        assert_eq!(kind("ERROR|LookupError: No results found for song: Daft Punk - Veridis Quo"), Some(ErrorKind::NoMatch));
+        assert_eq!(
+            kind("requests.exceptions.ConnectionError: HTTPSConnectionPool(host='api.spotify.com', port=443): Max retries exceeded"),
+            Some(ErrorKind::Network)
+        );
+        assert_eq!(kind("ERROR|FFmpegError: Failed to convert, ffmpeg exited with code 1"), Some(ErrorKind::Ffmpeg));
+        assert_eq!(kind("OSError: [Errno 28] No space left on device"), Some(ErrorKind::Disk));
+        assert_eq!(kind("PermissionError: [Errno 13] Permission denied: '/music/song.mp3'"), Some(ErrorKind::Disk));
+        assert_eq!(kind("ERROR|Something nobody has seen before"), None);
+    }
+
+    #[test]
+    fn spots_transient_errors() {
+        assert!(is_transient_error("ERROR|http status: 429, code:-1 - Max Retries, reason: too many 429 error responses"));
+        assert!(is_transient_error("requests.exceptions.ReadTimeout: HTTPSConnectionPool(host='api.spotify.com', port=443): Read timed out"));
+        assert!(is_transient_error("ERROR|http status: 503, code:-1 - Service Unavailable"));
+        assert!(!is_transient_error("ERROR|SpotifyOauthError: error: invalid_client, error_description: Invalid client"));
+        assert!(!is_transient_error("ERROR|LookupError: No results found for song: Daft Punk - Veridis Quo"));
+    }
+
+    #[test]
+    fn extracts_song_count() {
+        assert_eq!(extract_song_count("Found 42 songs in Road Trip"), Some(42));
+        assert_eq!(extract_song_count("Found songs in Road Trip"), None);
+        assert_eq!(extract_song_count("Found 3 of 12 songs"), Some(12));
+        assert_eq!(extract_song_count("Found 007 songs"), Some(7));
+        assert_eq!(extract_song_count("Found 1 song in Daft Punk - One More Time (Track)"), Some(1));
+        assert_eq!(extract_song_count("Found 1,024 songs in Everything (Playlist)"), Some(1024));
+        // Only whole words count
+        assert_eq!(extract_song_count("Found 12songs"), None);
+    }
+
+    #[test]
+    fn ignores_other_numbers_in_found_lines() {
+        assert_eq!(event("INFO|Found 2 songs on playlist 3"), SpotdlEvent::Found { count: 2 });
+        assert_eq!(event("INFO|Found 50 songs in Top 50 - Global (Playlist)"), SpotdlEvent::Found { count: 50 });
+        assert_eq!(event("INFO|Found 7 songs in 1989 (Taylor's Version) (Album)"), SpotdlEvent::Found { count: 7 });
+        assert_eq!(event("INFO|Found 14 song(s) in 2 Become 1 (Playlist)"), SpotdlEvent::Found { count: 14 });
+        assert_eq!(event("INFO|Found 3 songs, 2 of them new"), SpotdlEvent::Found { count: 3 });
+    }
+
+    #[test]
+    fn detects_missing_ffmpeg() {
+        assert_eq!(
+            event("ERROR|FFmpegError: FFmpeg is not installed. Please run `spotdl --download-ffmpeg`"),
+            SpotdlEvent::FfmpegMissing
+        );
+        assert_eq!(event("/bin/sh: 1: ffmpeg: not found"), SpotdlEvent::FfmpegMissing);
+        assert_eq!(event("INFO|Converting with ffmpeg"), SpotdlEvent::Converting { track: None });
+    }
+
+    #[test]
+    fn query_lines_are_not_conversions() {
+        assert_eq!(event("INFO|Processing query: https://open.spotify.com/album/2noRn2Aes5aoNVsU6iWThc"), SpotdlEvent::Other);
+        assert_eq!(event("INFO|Processing 12 songs"), SpotdlEvent::Other);
+    }
+}
@@ -0,0 +1,143 @@
+// Optional local websocket that mirrors the "download-progress" events.
+//
+// The server is off by default and only ever binds to 127.0.0.1, so it is
+// reachable by scripts and dashboards on the same machine but never from the
+// network. Clients are write-only: every ProgressPayload is pushed to them as
+// a JSON text frame and anything they send is ignored. Browsers always send an
+// Origin header, so handshakes that carry one are refused: otherwise any web page
+// open on the machine could read the user's downloads.
+use std::io::ErrorKind;
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex, atomic::{AtomicBool, Ordering}, mpsc};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use serde::Serialize;
+use tungstenite::handshake::server::{ErrorResponse, Request, Response};
+use tungstenite::http::{StatusCode, header};
+use tungstenite::{Message, WebSocket};
+
+type Clients = Arc<Mutex<Vec<WebSocket<TcpStream>>>>;
+
+struct RunningServer {
+    port: u16,
+    stop: Arc<AtomicBool>,
+    clients: Clients,
+    accept_thread: JoinHandle<()>,
+    // Payloads go out from their own thread, so a slow client never holds up the download
+    messages: mpsc::Sender<String>,
+    send_thread: JoinHandle<()>,
+}
+
+#[derive(Default)]
+pub struct ProgressServer {
+    running: Mutex<Option<RunningServer>>,
+}
+
+impl ProgressServer {
+    /// Bind to localhost and start accepting clients, returning the bound port
+    pub fn start(&self, port: u16) -> Result<u16, String> {
+        let mut running = self.running.lock()
+            .map_err(|_| "Progress server state is unavailable".to_string())?;
+        if let Some(server) = running.as_ref() {
+            return Err(format!("Progress server is already running on port {}", server.port));
+        }
+
+        let listener = TcpListener::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, port)))
+            .map_err(|e| format!("Failed to start progress server: {}", e))?;
+        let port = listener.local_addr().map(|addr| addr.port()).unwrap_or(port);
+
+        // Poll the listener so the accept loop can notice a stop request
+        listener.set_nonblocking(true)
+            .map_err(|e| format!("Failed to start progress server: {}", e))?;
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let clients: Clients = Arc::new(Mutex::new(Vec::new()));
+        let accept_stop = Arc::clone(&stop);
+        let accept_clients = Arc::clone(&clients);
+        let accept_thread = thread::spawn(move || accept_clients_loop(listener, accept_stop, accept_clients));
+        let (messages, receiver) = mpsc::channel();
+        let send_clients = Arc::clone(&clients);
+        let send_thread = thread::spawn(move || send_loop(receiver, send_clients));
+
+        *running = Some(RunningServer { port, stop, clients, accept_thread, messages, send_thread });
+        Ok(port)
+    }
+
+    /// Stop accepting clients and close every open connection
+    pub fn stop(&self) -> Result<(), String> {
+        let server = self.running.lock()
+            .map_err(|_| "Progress server state is unavailable".to_string())?
+            .take()
+            .ok_or("Progress server is not running")?;
+
+        let RunningServer { stop, clients, accept_thread, messages, send_thread, .. } = server;
+        stop.store(true, Ordering::SeqCst);
+        let _ = accept_thread.join();
+        // The send thread ends once it has passed on what was already queued
+        drop(messages);
+        let _ = send_thread.join();
+
+        if let Ok(mut clients) = clients.lock() {
+            for mut socket in clients.drain(..) {
+                let _ = socket.close(None);
+                let _ = socket.flush();
+            }
+        }
+        Ok(())
+    }
+
+    /// Queue a payload for every connected client
+    pub fn broadcast<T: Serialize>(&self, payload: &T) {
+        let Ok(running) = self.running.lock() else { return };
+        let Some(server) = running.as_ref() else { return };
+        if let Ok(json) = serde_json::to_string(payload) {
+            let _ = server.messages.send(json);
+        }
+    }
+}
+
+/// Send each queued payload to every client, dropping the ones that went away or stall
+fn send_loop(messages: mpsc::Receiver<String>, clients: Clients) {
+    for json in messages {
+        let Ok(mut clients) = clients.lock() else { return };
+        clients.retain_mut(|socket| socket.send(Message::text(json.clone())).is_ok());
+    }
+}
+
+/// Refuse handshakes from web pages, which browsers mark with an Origin header
+#[allow(clippy::result_large_err)]  // tungstenite's handshake callback signature
+fn reject_browsers(request: &Request, response: Response) -> Result<Response, ErrorResponse> {
+    if !request.headers().contains_key(header::ORIGIN) {
+        return Ok(response);
+    }
+    let mut error = ErrorResponse::new(Some("Connections from web pages are not allowed".to_string()));
+    *error.status_mut() = StatusCode::FORBIDDEN;
+    Err(error)
+}
+
+fn accept_clients_loop(listener: TcpListener, stop: Arc<AtomicBool>, clients: Clients) {
+    while !stop.load(Ordering::SeqCst) {
+        match listener.accept() {
+            Ok((stream, _)) => {
+                // Handshake on a blocking socket, but never let a slow client stall progress
+                if stream.set_nonblocking(false).is_err() {
+                    continue;
+                }
+                let _ = stream.set_read_timeout(Some(Duration::from_secs(5)));
+                let _ = stream.set_write_timeout(Some(Duration::from_secs(1)));
+
+                if let Ok(socket) = tungstenite::accept_hdr(stream, reject_browsers) {
+                    if let Ok(mut clients) = clients.lock() {
+                        clients.push(socket);
+                    }
+                }
+            }
+            Err(e) if e.kind() == ErrorKind::WouldBlock => {
+                thread::sleep(Duration::from_millis(100));
+            }
+            Err(_) => {
+                thread::sleep(Duration::from_millis(100));
+            }
+        }
+    }
+}
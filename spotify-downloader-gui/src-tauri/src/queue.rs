@@ -0,0 +1,241 @@
+// Download queue: URLs are downloaded one after another by a single worker thread.
+use std::collections::VecDeque;
+use std::sync::{Mutex, atomic::{AtomicBool, AtomicU64, Ordering}, mpsc};
+use std::thread;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+use crate::{DownloadOptions, DownloadSummary, quiet_hours, run_download};
+use crate::spotify_url::{ContentKind, validate_spotify_url};
+
+type DownloadResult = Result<DownloadSummary, String>;
+
+const QUEUE_CANCELLED: &str = "The download queue was cancelled";
+
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QueueStatus {
+    Pending,
+    Active,
+}
+
+#[derive(Clone, Serialize)]
+pub struct QueuedDownload {
+    pub id: u64,
+    pub url: String,
+    pub queries: Vec<String>,
+    pub content_type: ContentKind,
+    pub options: DownloadOptions,
+    pub status: QueueStatus,
+    // Set when a caller is waiting for this download's outcome
+    #[serde(skip)]
+    result: Option<mpsc::Sender<DownloadResult>>,
+}
+
+#[derive(Clone, Serialize)]
+struct QueueProgressPayload {
+    id: u64,
+    url: String,
+    index: u32,  // 1-based position of the active item in this run of the queue
+    total: u32,
+}
+
+#[derive(Default)]
+pub struct DownloadQueue {
+    pending: Mutex<VecDeque<QueuedDownload>>,
+    active: Mutex<Option<QueuedDownload>>,
+    next_id: AtomicU64,
+    worker_running: AtomicBool,
+    stop_requested: AtomicBool,  // cancel_all, so the worker stops instead of starting what it just took
+}
+
+impl DownloadQueue {
+    pub fn list(&self) -> Vec<QueuedDownload> {
+        let active = self.active.lock().ok().and_then(|active| active.clone());
+        let pending: Vec<_> = self.pending.lock().map(|pending| pending.iter().cloned().collect()).unwrap_or_default();
+        active.into_iter().chain(pending).collect()
+    }
+
+    pub fn remove(&self, id: u64) -> Result<(), String> {
+        let mut pending = self.pending.lock().map_err(|_| "Download queue is unavailable".to_string())?;
+        if let Some(index) = pending.iter().position(|item| item.id == id) {
+            pending.remove(index);
+            return Ok(());
+        }
+        if self.is_active(id) {
+            return Err("This download is already running, cancel it instead".to_string());
+        }
+        Err(format!("No queued download with id {}", id))
+    }
+
+    pub fn move_to(&self, id: u64, position: usize) -> Result<(), String> {
+        let mut pending = self.pending.lock().map_err(|_| "Download queue is unavailable".to_string())?;
+        let Some(index) = pending.iter().position(|item| item.id == id) else {
+            if self.is_active(id) {
+                return Err("This download is already running and can't be moved".to_string());
+            }
+            return Err(format!("No queued download with id {}", id));
+        };
+
+        if let Some(item) = pending.remove(index) {
+            let position = position.min(pending.len());
+            pending.insert(position, item);
+        }
+        Ok(())
+    }
+
+    /// Drop every pending download, telling callers waiting on one that it was cancelled
+    fn clear_pending(&self) -> u32 {
+        let items: Vec<QueuedDownload> = self.pending.lock().map(|mut pending| pending.drain(..).collect()).unwrap_or_default();
+        for item in &items {
+            if let Some(result) = &item.result {
+                let _ = result.send(Err(QUEUE_CANCELLED.to_string()));
+            }
+        }
+        items.len() as u32
+    }
+
+    fn is_active(&self, id: u64) -> bool {
+        self.active.lock().is_ok_and(|active| active.as_ref().is_some_and(|item| item.id == id))
+    }
+}
+
+/// A link read from a list file, ready to be enqueued
+#[derive(Clone, Serialize)]
+pub struct ImportedUrl {
+    pub line: usize,  // 1-based
+    pub url: String,
+    pub content_type: ContentKind,
+}
+
+/// A line of a list file that isn't a usable Spotify link, and why
+#[derive(Clone, Serialize)]
+pub struct InvalidLine {
+    pub line: usize,  // 1-based
+    pub text: String,
+    pub error: String,
+}
+
+#[derive(Clone, Serialize)]
+pub struct UrlImport {
+    pub items: Vec<ImportedUrl>,
+    pub invalid: Vec<InvalidLine>,
+}
+
+/// Read one Spotify link per line. Blank lines and `#` comments are skipped, and
+/// lines that aren't links are reported instead of failing the whole list
+pub fn parse_url_list(contents: &str) -> UrlImport {
+    let mut import = UrlImport { items: Vec::new(), invalid: Vec::new() };
+    for (index, text) in contents.lines().enumerate() {
+        let text = text.trim();
+        if text.is_empty() || text.starts_with('#') {
+            continue;
+        }
+
+        match validate_spotify_url(text) {
+            Ok(content_type) => import.items.push(ImportedUrl { line: index + 1, url: text.to_string(), content_type }),
+            Err(error) => import.invalid.push(InvalidLine { line: index + 1, text: text.to_string(), error }),
+        }
+    }
+    import
+}
+
+/// Empty the queue and stop the worker once the active download ends, returns how many
+/// pending downloads were dropped. Killing the active download is up to the caller
+pub fn cancel_all(app: &AppHandle) -> u32 {
+    let queue = app.state::<DownloadQueue>();
+    if queue.worker_running.load(Ordering::SeqCst) {
+        queue.stop_requested.store(true, Ordering::SeqCst);
+    }
+    queue.clear_pending()
+}
+
+/// Add a download to the end of the queue, starting the worker if it's idle
+pub fn enqueue(
+    app: &AppHandle,
+    url: String,
+    queries: Vec<String>,
+    content_type: ContentKind,
+    options: DownloadOptions,
+    result: Option<mpsc::Sender<DownloadResult>>,
+) -> u64 {
+    let queue = app.state::<DownloadQueue>();
+    let id = queue.next_id.fetch_add(1, Ordering::SeqCst) + 1;
+    if let Ok(mut pending) = queue.pending.lock() {
+        pending.push_back(QueuedDownload { id, url, queries, content_type, options, status: QueueStatus::Pending, result });
+    }
+
+    if !queue.worker_running.swap(true, Ordering::SeqCst) {
+        spawn_worker(app.clone());
+    }
+    id
+}
+
+/// Queue a download and block until it has run
+pub fn run_now(
+    app: &AppHandle,
+    url: String,
+    queries: Vec<String>,
+    content_type: ContentKind,
+    options: DownloadOptions,
+) -> DownloadResult {
+    let (sender, receiver) = mpsc::channel();
+    enqueue(app, url, queries, content_type, options, Some(sender));
+    receiver.recv().unwrap_or_else(|_| Err("Download was removed from the queue".to_string()))
+}
+
+fn spawn_worker(app: AppHandle) {
+    thread::spawn(move || {
+        let queue = app.state::<DownloadQueue>();
+        let mut index = 0;
+
+        loop {
+            // Nothing new starts during quiet hours. A cancel_all meanwhile empties the queue,
+            // so the worker stops below
+            quiet_hours::wait_until_over(&app, || queue.stop_requested.load(Ordering::SeqCst));
+
+            // The empty check and clearing the flags happen under the same lock enqueue pushes with
+            let Ok(mut pending) = queue.pending.lock() else {
+                queue.worker_running.store(false, Ordering::SeqCst);
+                return;
+            };
+            let Some(mut item) = pending.pop_front() else {
+                queue.worker_running.store(false, Ordering::SeqCst);
+                queue.stop_requested.store(false, Ordering::SeqCst);
+                return;
+            };
+            let remaining = pending.len() as u32;
+            drop(pending);
+
+            // cancel_all came in while this one was being taken off the queue
+            if queue.stop_requested.swap(false, Ordering::SeqCst) {
+                if let Some(result) = item.result.take() {
+                    let _ = result.send(Err(QUEUE_CANCELLED.to_string()));
+                }
+                continue;
+            }
+
+            index += 1;
+            item.status = QueueStatus::Active;
+            let result = item.result.take();
+            if let Ok(mut active) = queue.active.lock() {
+                *active = Some(item.clone());
+            }
+            let _ = app.emit("queue-progress", QueueProgressPayload {
+                id: item.id,
+                url: item.url.clone(),
+                index,
+                total: index + remaining,
+            });
+
+            let outcome = run_download(app.clone(), item.url, item.queries, item.content_type, item.options);
+            if let Ok(mut active) = queue.active.lock() {
+                *active = None;
+            }
+            // The queue is already empty after a cancel_all, so the loop ends
+            queue.stop_requested.store(false, Ordering::SeqCst);
+            if let Some(result) = result {
+                let _ = result.send(outcome);
+            }
+        }
+    });
+}
@@ -0,0 +1,107 @@
+// Quiet hours: a daily time window during which downloads are held paused.
+use std::sync::{Mutex, atomic::Ordering};
+use std::thread;
+use std::time::Duration;
+use chrono::{Local, NaiveTime};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager};
+use crate::settings::Settings;
+use crate::{DownloadState, PAUSE_QUIET_HOURS, pause_child, resume_child};
+
+// How often the scheduler looks at the clock
+const CHECK_INTERVAL: Duration = Duration::from_secs(15);
+// How often a download waiting for quiet hours to end checks again, short so a cancel isn't kept waiting
+const WAIT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Daily window in local "HH:MM" time; a start after the end wraps past midnight
+#[derive(Clone, Serialize, Deserialize)]
+pub struct QuietHours {
+    pub start: String,
+    pub end: String,
+}
+
+impl QuietHours {
+    pub fn validate(&self) -> Result<(), String> {
+        let (start, end) = self.bounds()?;
+        if start == end {
+            return Err("Quiet hours start and end must differ".to_string());
+        }
+        Ok(())
+    }
+
+    fn bounds(&self) -> Result<(NaiveTime, NaiveTime), String> {
+        Ok((parse_time(&self.start)?, parse_time(&self.end)?))
+    }
+
+    fn contains(&self, now: NaiveTime) -> bool {
+        let Ok((start, end)) = self.bounds() else { return false };
+        if start <= end {
+            now >= start && now < end
+        } else {
+            now >= start || now < end
+        }
+    }
+}
+
+fn parse_time(value: &str) -> Result<NaiveTime, String> {
+    NaiveTime::parse_from_str(value.trim(), "%H:%M")
+        .map_err(|_| format!("Invalid time '{}', expected HH:MM", value))
+}
+
+/// The quiet hours window, if the current time is inside it
+fn current(app: &AppHandle) -> Option<QuietHours> {
+    app.state::<Mutex<Settings>>()
+        .lock()
+        .ok()
+        .and_then(|settings| settings.quiet_hours.clone())
+        .filter(|window| window.contains(Local::now().time()))
+}
+
+/// Hold off starting a download until quiet hours are over. Returns false when
+/// `stop` asks to give up first
+pub fn wait_until_over(app: &AppHandle, stop: impl Fn() -> bool) -> bool {
+    let mut waited = None;
+    while let Some(window) = current(app) {
+        if stop() {
+            return false;
+        }
+        if waited.is_none() {
+            let _ = app.emit("quiet-hours-paused", window.clone());
+        }
+        waited = Some(window);
+        thread::sleep(WAIT_INTERVAL);
+    }
+    // A pause left over from a download that ended during quiet hours is over too
+    let held = resume_child(&app.state::<DownloadState>(), PAUSE_QUIET_HOURS);
+    if held || waited.is_some() {
+        let _ = app.emit("quiet-hours-resumed", waited);
+    }
+    true
+}
+
+/// Watch the clock and pause/resume the active download around quiet hours
+pub fn spawn_scheduler(app: AppHandle) {
+    thread::spawn(move || loop {
+        thread::sleep(CHECK_INTERVAL);
+
+        let window = app.state::<Mutex<Settings>>()
+            .lock()
+            .ok()
+            .and_then(|settings| settings.quiet_hours.clone());
+        let state = app.state::<DownloadState>();
+        let paused = state.pause_reasons.load(Ordering::SeqCst) & PAUSE_QUIET_HOURS != 0;
+
+        match window {
+            Some(window) if window.contains(Local::now().time()) => {
+                if !paused && pause_child(&state, PAUSE_QUIET_HOURS) {
+                    let _ = app.emit("quiet-hours-paused", window);
+                }
+            }
+            window => {
+                if paused && resume_child(&state, PAUSE_QUIET_HOURS) {
+                    let _ = app.emit("quiet-hours-resumed", window);
+                }
+            }
+        }
+    });
+}
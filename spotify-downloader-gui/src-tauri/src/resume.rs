@@ -0,0 +1,104 @@
+// The running download, saved to the app data directory so one cut short by a crash
+// can be picked up after a restart. Songs that made it into the folder are left out
+// the second time, by the folder's manifest or spotdl's own existing-file check.
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+use crate::DownloadOptions;
+use crate::spotify_url::ContentKind;
+
+const RESUME_FILE: &str = "interrupted-download.json";
+
+// Finished songs are written out at most this often
+const SAVE_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct InterruptedDownload {
+    pub url: String,
+    pub queries: Vec<String>,
+    pub content_type: ContentKind,
+    pub options: DownloadOptions,
+    pub output_folder: String,
+    pub started_at: String,  // RFC 3339, local time
+    pub completed: Vec<String>,  // songs finished before it stopped, as spotdl named them
+}
+
+/// What the UI is told about an interrupted download
+#[derive(Clone, Serialize)]
+pub struct ResumeInfo {
+    pub url: String,
+    pub content_type: ContentKind,
+    pub output_folder: String,
+    pub started_at: String,
+    pub completed: u32,
+}
+
+impl From<&InterruptedDownload> for ResumeInfo {
+    fn from(download: &InterruptedDownload) -> Self {
+        Self {
+            url: download.url.clone(),
+            content_type: download.content_type,
+            output_folder: download.output_folder.clone(),
+            started_at: download.started_at.clone(),
+            completed: download.completed.len() as u32,
+        }
+    }
+}
+
+fn resume_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = app.path().app_data_dir()
+        .map_err(|e| format!("Failed to resolve data directory: {}", e))?;
+    Ok(dir.join(RESUME_FILE))
+}
+
+/// The download that was running when the app last stopped, if it never finished
+pub fn load(app: &AppHandle) -> Option<InterruptedDownload> {
+    let contents = fs::read_to_string(resume_path(app).ok()?).ok()?;
+    serde_json::from_str(&contents).ok()
+}
+
+/// Forget the saved download, once it finished or was cancelled
+pub fn clear(app: &AppHandle) -> Result<(), String> {
+    let path = resume_path(app)?;
+    match fs::remove_file(&path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(format!("Failed to clear interrupted download: {}", e)),
+        _ => Ok(()),
+    }
+}
+
+/// Keeps the saved copy of the running download up to date
+pub struct Tracker {
+    path: PathBuf,
+    download: InterruptedDownload,
+    last_saved: Instant,
+}
+
+impl Tracker {
+    /// Save the download as it starts, None when the data directory can't be written
+    pub fn start(app: &AppHandle, download: InterruptedDownload) -> Option<Self> {
+        let path = resume_path(app).ok()?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).ok()?;
+        }
+        let tracker = Self { path, download, last_saved: Instant::now() };
+        tracker.save().ok()?;
+        Some(tracker)
+    }
+
+    /// Note a finished song, written out once SAVE_INTERVAL has passed since the last save
+    pub fn complete(&mut self, track: &str) {
+        self.download.completed.push(track.to_string());
+        if self.last_saved.elapsed() >= SAVE_INTERVAL {
+            self.last_saved = Instant::now();
+            let _ = self.save();
+        }
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let contents = serde_json::to_string_pretty(&self.download)
+            .map_err(|e| format!("Failed to serialize interrupted download: {}", e))?;
+        fs::write(&self.path, contents).map_err(|e| format!("Failed to write interrupted download: {}", e))
+    }
+}
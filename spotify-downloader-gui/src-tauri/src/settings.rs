@@ -0,0 +1,373 @@
+// Persisted user settings, stored as JSON in the app config directory.
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tauri::{AppHandle, Manager};
+use crate::quiet_hours::QuietHours;
+use crate::spotify_url::ContentKind;
+use crate::thermal::ThermalGuard;
+use crate::{AUDIO_FORMATS, BITRATES, validate_output_template};
+
+const SETTINGS_FILE: &str = "settings.json";
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub download_path: Option<String>,
+    pub threads: u32,
+    pub conversion_threads: Option<u32>,  // fewer threads for formats ffmpeg re-encodes
+    pub format: String,
+    pub bitrate: Option<String>,
+    pub quiet_hours: Option<QuietHours>,
+    pub thermal_guard: Option<ThermalGuard>,
+    pub slow_download: SlowDownloadConfig,
+    pub spotdl_path: Option<String>,
+    pub keep_download_logs: bool,
+    pub notify_on_complete: bool,
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+    pub min_free_space_mb: u64,  // a download that would leave less free space is refused
+    pub proxy: Option<String>,  // e.g. "http://proxy.example.com:8080" or "socks5://127.0.0.1:1080"
+    pub ffmpeg_path: Option<String>,  // passed to spotdl as --ffmpeg
+    pub extra_path: Vec<String>,  // folders put in front of PATH for spotdl, python and ffmpeg
+    pub output_templates: OutputTemplates,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            download_path: None,
+            threads: 4,
+            conversion_threads: None,
+            format: "mp3".to_string(),
+            bitrate: None,
+            quiet_hours: None,
+            thermal_guard: None,
+            slow_download: SlowDownloadConfig::default(),
+            spotdl_path: None,
+            keep_download_logs: false,
+            notify_on_complete: false,
+            client_id: None,
+            client_secret: None,
+            min_free_space_mb: 500,
+            proxy: None,
+            ffmpeg_path: None,
+            extra_path: Vec::new(),
+            output_templates: OutputTemplates::default(),
+        }
+    }
+}
+
+impl Settings {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(1..=MAX_THREADS).contains(&self.threads) {
+            return Err(format!("Threads must be between 1 and {}", MAX_THREADS));
+        }
+        if self.conversion_threads.is_some_and(|n| !(1..=MAX_THREADS).contains(&n)) {
+            return Err(format!("Conversion threads must be between 1 and {}", MAX_THREADS));
+        }
+        if !AUDIO_FORMATS.contains(&self.format.as_str()) {
+            return Err(format!("Unsupported audio format '{}'", self.format));
+        }
+        if let Some(bitrate) = &self.bitrate {
+            if !BITRATES.contains(&bitrate.as_str()) {
+                return Err(format!("Invalid bitrate '{}'", bitrate));
+            }
+        }
+        if self.client_id.is_some() != self.client_secret.is_some() {
+            return Err("Set both the Spotify client id and client secret, or neither".to_string());
+        }
+        if let Some(proxy) = &self.proxy {
+            validate_proxy(proxy)?;
+        }
+        if let Some(ffmpeg) = &self.ffmpeg_path {
+            if !Path::new(ffmpeg).is_file() {
+                return Err(format!("ffmpeg was not found at {}", ffmpeg));
+            }
+        }
+        if self.extra_path.iter().any(|dir| dir.trim().is_empty()) {
+            return Err("Extra PATH entries can't be empty".to_string());
+        }
+        self.output_templates.validate()?;
+        if let Some(window) = &self.quiet_hours {
+            window.validate()?;
+        }
+        if let Some(guard) = &self.thermal_guard {
+            guard.validate()?;
+        }
+        self.slow_download.validate()
+    }
+}
+
+// Upper bound for the parallel downloads slider
+pub const MAX_THREADS: u32 = 16;
+
+// Proxy schemes yt-dlp and spotdl's HTTP clients understand
+const PROXY_SCHEMES: &[&str] = &["http", "https", "socks4", "socks5", "socks5h"];
+
+/// A proxy must be a URL with a supported scheme and a host
+pub fn validate_proxy(proxy: &str) -> Result<(), String> {
+    let (scheme, rest) = proxy.split_once("://")
+        .ok_or_else(|| format!("Proxy '{}' must be a URL like http://host:port", proxy))?;
+    if !PROXY_SCHEMES.contains(&scheme.to_lowercase().as_str()) {
+        return Err(format!("Unsupported proxy scheme '{}'. Use {}", scheme, PROXY_SCHEMES.join(", ")));
+    }
+    let host = rest.rsplit_once('@').map_or(rest, |(_, host)| host);
+    if host.trim_end_matches('/').is_empty() {
+        return Err(format!("Proxy '{}' has no host", proxy));
+    }
+    Ok(())
+}
+
+/// The --output template a download gets when it doesn't set its own, by content type,
+/// with `default` for the types that have none
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OutputTemplates {
+    pub default: Option<String>,
+    pub track: Option<String>,
+    pub album: Option<String>,  // e.g. "{artist}/{album}/{title}.{output-ext}"
+    pub playlist: Option<String>,
+    pub artist: Option<String>,
+}
+
+impl OutputTemplates {
+    pub fn for_kind(&self, kind: ContentKind) -> Option<&str> {
+        let template = match kind {
+            ContentKind::Track => &self.track,
+            ContentKind::Album => &self.album,
+            ContentKind::Playlist => &self.playlist,
+            ContentKind::Artist => &self.artist,
+        };
+        [template, &self.default]
+            .into_iter()
+            .find_map(|template| template.as_deref().map(str::trim).filter(|t| !t.is_empty()))
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        [&self.default, &self.track, &self.album, &self.playlist, &self.artist]
+            .into_iter()
+            .flatten()
+            .map(|template| template.trim())
+            .filter(|template| !template.is_empty())
+            .try_for_each(validate_output_template)
+    }
+}
+
+/// A download averaging under `min_songs_per_min` for `window_secs` is reported as slow
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SlowDownloadConfig {
+    pub min_songs_per_min: f64,
+    pub window_secs: u64,
+}
+
+impl Default for SlowDownloadConfig {
+    fn default() -> Self {
+        Self {
+            min_songs_per_min: 0.5,
+            window_secs: 120,
+        }
+    }
+}
+
+impl SlowDownloadConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.min_songs_per_min.is_finite() || self.min_songs_per_min < 0.0 {
+            return Err("Slow download threshold must be a positive number of songs/min".to_string());
+        }
+        if self.window_secs == 0 {
+            return Err("Slow download window must be at least one second".to_string());
+        }
+        Ok(())
+    }
+}
+
+fn settings_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = app.path().app_config_dir()
+        .map_err(|e| format!("Failed to resolve config directory: {}", e))?;
+    Ok(dir.join(SETTINGS_FILE))
+}
+
+#[derive(Clone, Serialize)]
+pub struct RepairReport {
+    pub changed: bool,
+    pub changes: Vec<String>,
+}
+
+/// Read the settings file, returning defaults when it doesn't exist yet.
+/// Malformed content never fails the load, broken parts fall back to defaults.
+pub fn load(app: &AppHandle) -> Result<Settings, String> {
+    let path = settings_path(app)?;
+    if !path.exists() {
+        return Ok(Settings::default());
+    }
+
+    let contents = fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read settings: {}", e))?;
+    Ok(parse_lenient(&contents).0)
+}
+
+/// Re-parse the settings file leniently and rewrite it in a valid shape
+pub fn repair(app: &AppHandle) -> Result<(Settings, RepairReport), String> {
+    let path = settings_path(app)?;
+    let (settings, changes) = if path.exists() {
+        let contents = fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read settings: {}", e))?;
+        parse_lenient(&contents)
+    } else {
+        (Settings::default(), vec!["Created a new settings file with defaults".to_string()])
+    };
+
+    save(app, &settings)?;
+    let report = RepairReport { changed: !changes.is_empty(), changes };
+    Ok((settings, report))
+}
+
+/// Parse settings field by field: unknown fields are dropped and missing or
+/// invalid ones take their default, with a note describing each change
+fn parse_lenient(contents: &str) -> (Settings, Vec<String>) {
+    let mut changes = Vec::new();
+    let Ok(Value::Object(defaults)) = serde_json::to_value(Settings::default()) else {
+        return (Settings::default(), changes);
+    };
+
+    let fields = match serde_json::from_str::<Value>(contents) {
+        Ok(Value::Object(fields)) => Some(fields),
+        Ok(_) => {
+            changes.push("Settings file was not a JSON object, reset to defaults".to_string());
+            None
+        }
+        Err(e) => {
+            changes.push(format!("Settings file could not be parsed ({}), reset to defaults", e));
+            None
+        }
+    };
+
+    let mut merged = defaults.clone();
+    if let Some(fields) = fields {
+        for key in defaults.keys() {
+            if !fields.contains_key(key) {
+                changes.push(format!("Added missing field '{}'", key));
+            }
+        }
+
+        for (key, value) in fields {
+            if !defaults.contains_key(&key) {
+                changes.push(format!("Dropped unknown field '{}'", key));
+                continue;
+            }
+
+            // Only keep the value if it deserializes on its own
+            let mut candidate: Map<String, Value> = merged.clone();
+            candidate.insert(key.clone(), value);
+            if serde_json::from_value::<Settings>(Value::Object(candidate.clone())).is_ok() {
+                merged = candidate;
+            } else {
+                changes.push(format!("Reset invalid field '{}' to its default", key));
+            }
+        }
+    }
+
+    let mut settings: Settings = serde_json::from_value(Value::Object(merged)).unwrap_or_default();
+    if settings.quiet_hours.as_ref().is_some_and(|window| window.validate().is_err()) {
+        settings.quiet_hours = None;
+        changes.push("Cleared quiet hours with an invalid time window".to_string());
+    }
+    if settings.thermal_guard.as_ref().is_some_and(|guard| guard.validate().is_err()) {
+        settings.thermal_guard = None;
+        changes.push("Turned off the thermal guard with invalid limits".to_string());
+    }
+    let defaults = Settings::default();
+    if !(1..=MAX_THREADS).contains(&settings.threads) {
+        settings.threads = defaults.threads;
+        changes.push("Reset the thread count to its default".to_string());
+    }
+    if settings.conversion_threads.is_some_and(|n| !(1..=MAX_THREADS).contains(&n)) {
+        settings.conversion_threads = None;
+        changes.push("Cleared an out-of-range conversion thread count".to_string());
+    }
+    if !AUDIO_FORMATS.contains(&settings.format.as_str()) {
+        settings.format = defaults.format;
+        changes.push("Reset the audio format to its default".to_string());
+    }
+    if settings.bitrate.as_deref().is_some_and(|bitrate| !BITRATES.contains(&bitrate)) {
+        settings.bitrate = None;
+        changes.push("Cleared an invalid bitrate".to_string());
+    }
+    if settings.client_id.is_some() != settings.client_secret.is_some() {
+        settings.client_id = None;
+        settings.client_secret = None;
+        changes.push("Cleared incomplete Spotify credentials".to_string());
+    }
+    if settings.proxy.as_deref().is_some_and(|proxy| validate_proxy(proxy).is_err()) {
+        settings.proxy = None;
+        changes.push("Cleared an invalid proxy".to_string());
+    }
+    if settings.extra_path.iter().any(|dir| dir.trim().is_empty()) {
+        settings.extra_path.retain(|dir| !dir.trim().is_empty());
+        changes.push("Removed empty extra PATH entries".to_string());
+    }
+    if settings.output_templates.validate().is_err() {
+        settings.output_templates = OutputTemplates::default();
+        changes.push("Cleared output templates without {title} or {trackid}".to_string());
+    }
+    if settings.slow_download.validate().is_err() {
+        settings.slow_download = SlowDownloadConfig::default();
+        changes.push("Reset the slow download threshold to its default".to_string());
+    }
+
+    (settings, changes)
+}
+
+/// Write the settings file, creating the config directory if needed
+pub fn save(app: &AppHandle, settings: &Settings) -> Result<(), String> {
+    let path = settings_path(app)?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("Failed to create config directory: {}", e))?;
+    }
+
+    let contents = serde_json::to_string_pretty(settings)
+        .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+    fs::write(&path, contents).map_err(|e| format!("Failed to write settings: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_json_resets_to_defaults() {
+        let (settings, changes) = parse_lenient("{ \"threads\": 8,");
+        assert_eq!(settings.threads, Settings::default().threads);
+        assert_eq!(changes.len(), 1);
+        assert!(changes[0].starts_with("Settings file could not be parsed"));
+    }
+
+    #[test]
+    fn non_object_root_resets_to_defaults() {
+        let (settings, changes) = parse_lenient("[1, 2, 3]");
+        assert_eq!(settings.format, "mp3");
+        assert_eq!(changes, vec!["Settings file was not a JSON object, reset to defaults".to_string()]);
+    }
+
+    #[test]
+    fn drops_unknown_and_resets_mistyped_fields() {
+        let (settings, changes) = parse_lenient(r#"{ "format": "flac", "threads": "eight", "theme": "dark" }"#);
+        assert_eq!(settings.format, "flac");
+        assert_eq!(settings.threads, 4);
+        assert!(changes.contains(&"Dropped unknown field 'theme'".to_string()));
+        assert!(changes.contains(&"Reset invalid field 'threads' to its default".to_string()));
+        assert!(changes.contains(&"Added missing field 'download_path'".to_string()));
+    }
+
+    #[test]
+    fn resets_out_of_range_thread_counts() {
+        let (settings, changes) = parse_lenient(r#"{ "threads": 64, "conversion_threads": 0 }"#);
+        assert_eq!(settings.threads, 4);
+        assert_eq!(settings.conversion_threads, None);
+        assert!(changes.contains(&"Reset the thread count to its default".to_string()));
+        assert!(changes.contains(&"Cleared an out-of-range conversion thread count".to_string()));
+        assert!(settings.validate().is_ok());
+    }
+}
@@ -0,0 +1,115 @@
+// Recognizing Spotify links before they are handed to spotdl.
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ContentKind {
+    Track,
+    Album,
+    Playlist,
+    Artist,
+}
+
+impl ContentKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Track => "track",
+            Self::Album => "album",
+            Self::Playlist => "playlist",
+            Self::Artist => "artist",
+        }
+    }
+
+    fn from_segment(segment: &str) -> Option<Self> {
+        match segment {
+            "track" => Some(Self::Track),
+            "album" => Some(Self::Album),
+            "playlist" => Some(Self::Playlist),
+            "artist" => Some(Self::Artist),
+            _ => None,
+        }
+    }
+}
+
+/// Check that `url` is an open.spotify.com link or a `spotify:` URI and say what it points to
+pub fn validate_spotify_url(url: &str) -> Result<ContentKind, String> {
+    parse(url).map(|(kind, _)| kind)
+}
+
+/// The plain https://open.spotify.com/<kind>/<id> form of a link or `spotify:` URI,
+/// without share-link query parameters like ?si= or a localized /intl-xx/ path
+pub fn normalize_spotify_url(url: &str) -> Result<String, String> {
+    let (kind, id) = parse(url)?;
+    Ok(format!("https://open.spotify.com/{}/{}", kind.as_str(), id))
+}
+
+/// What a link points to and the id of it
+pub fn parse(url: &str) -> Result<(ContentKind, &str), String> {
+    let url = url.trim();
+    if url.is_empty() {
+        return Err("Please enter a Spotify URL".to_string());
+    }
+
+    let mut segments: Vec<&str> = if let Some(uri) = url.strip_prefix("spotify:") {
+        uri.split(':').collect()
+    } else {
+        let rest = url.strip_prefix("https://")
+            .or_else(|| url.strip_prefix("http://"))
+            .unwrap_or(url);
+        let (host, path) = rest.split_once('/').unwrap_or((rest, ""));
+        if !host.eq_ignore_ascii_case("open.spotify.com") {
+            return Err("Not a Spotify link. Paste a link from open.spotify.com or a spotify: URI".to_string());
+        }
+
+        let path = path.split(['?', '#']).next().unwrap_or("");
+        path.split('/').filter(|segment| !segment.is_empty()).collect()
+    };
+
+    // Localized links (/intl-de/track/...) and old user playlists (user:<name>:playlist:<id>)
+    if segments.first().is_some_and(|segment| segment.starts_with("intl-")) {
+        segments.remove(0);
+    }
+    if segments.first() == Some(&"user") && segments.len() > 2 {
+        segments.drain(..2);
+    }
+
+    let Some(kind) = segments.first().and_then(|segment| ContentKind::from_segment(segment)) else {
+        return Err("This Spotify link can't be downloaded, use a track, album, playlist or artist link".to_string());
+    };
+    let id = segments.get(1).copied().unwrap_or("");
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err(format!("This Spotify {} link is missing a valid id", kind.as_str()));
+    }
+
+    Ok((kind, id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_share_links() {
+        assert_eq!(
+            normalize_spotify_url(" https://open.spotify.com/intl-de/track/4uLU6hMCjMI75M1A2tKUQC?si=abc123&utm_source=copy-link "),
+            Ok("https://open.spotify.com/track/4uLU6hMCjMI75M1A2tKUQC".to_string())
+        );
+        assert_eq!(
+            normalize_spotify_url("spotify:playlist:37i9dQZF1DXcBWIGoYBM5M"),
+            Ok("https://open.spotify.com/playlist/37i9dQZF1DXcBWIGoYBM5M".to_string())
+        );
+        assert_eq!(
+            normalize_spotify_url("spotify:user:someone:playlist:37i9dQZF1DXcBWIGoYBM5M"),
+            Ok("https://open.spotify.com/playlist/37i9dQZF1DXcBWIGoYBM5M".to_string())
+        );
+        assert!(normalize_spotify_url("https://www.youtube.com/watch?v=dQw4w9WgXcQ").is_err());
+    }
+
+    #[test]
+    fn parses_kind_and_id() {
+        assert_eq!(parse("https://open.spotify.com/intl-fr/album/4m2880jivSbbyEGAKfITCa?si=x"), Ok((ContentKind::Album, "4m2880jivSbbyEGAKfITCa")));
+        assert_eq!(parse("spotify:artist:4tZwfgrHOc3mvqYlEYSvVi"), Ok((ContentKind::Artist, "4tZwfgrHOc3mvqYlEYSvVi")));
+        assert!(parse("https://open.spotify.com/track/").is_err());
+        assert!(parse("https://open.spotify.com/episode/4rOoJ6Egrf8K2IrywzwOMk").is_err());
+    }
+}
@@ -0,0 +1,85 @@
+// Thermal guard: pause the download while the CPU runs too hot.
+use std::sync::{Mutex, atomic::Ordering};
+use std::thread;
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager};
+use crate::settings::Settings;
+use crate::{DownloadState, PAUSE_THERMAL, pause_child, resume_child};
+
+// How often the guard samples the CPU temperature
+const CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Pause above `pause_above_celsius`, resume once back under `resume_below_celsius`
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ThermalGuard {
+    pub pause_above_celsius: f32,
+    pub resume_below_celsius: f32,
+}
+
+impl ThermalGuard {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(self.pause_above_celsius > 0.0 && self.pause_above_celsius < 150.0) {
+            return Err("Pause temperature must be between 0 and 150 °C".to_string());
+        }
+        if self.resume_below_celsius >= self.pause_above_celsius {
+            return Err("Resume temperature must be lower than the pause temperature".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Serialize)]
+struct ThermalPayload {
+    celsius: f32,
+}
+
+/// Hottest reading across the kernel's thermal zones, if any are exposed
+#[cfg(target_os = "linux")]
+pub fn cpu_temperature() -> Option<f32> {
+    std::fs::read_dir("/sys/class/thermal")
+        .ok()?
+        .flatten()
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with("thermal_zone"))
+        .filter_map(|entry| std::fs::read_to_string(entry.path().join("temp")).ok())
+        .filter_map(|raw| raw.trim().parse::<f32>().ok())
+        .map(|millidegrees| millidegrees / 1000.0)
+        .reduce(f32::max)
+}
+
+/// No sensor access on this platform, so the guard never triggers
+#[cfg(not(target_os = "linux"))]
+pub fn cpu_temperature() -> Option<f32> {
+    None
+}
+
+/// Watch the CPU temperature and pause/resume the active download around the limits
+pub fn spawn_monitor(app: AppHandle) {
+    thread::spawn(move || loop {
+        thread::sleep(CHECK_INTERVAL);
+
+        let state = app.state::<DownloadState>();
+        let paused = state.pause_reasons.load(Ordering::SeqCst) & PAUSE_THERMAL != 0;
+        let guard = app.state::<Mutex<Settings>>()
+            .lock()
+            .ok()
+            .and_then(|settings| settings.thermal_guard.clone());
+
+        let Some(guard) = guard else {
+            // Guard switched off while holding a pause
+            if paused && resume_child(&state, PAUSE_THERMAL) {
+                let _ = app.emit("thermal-resumed", ThermalPayload { celsius: cpu_temperature().unwrap_or(0.0) });
+            }
+            continue;
+        };
+        let Some(celsius) = cpu_temperature() else { continue };
+
+        if !paused && celsius >= guard.pause_above_celsius {
+            if pause_child(&state, PAUSE_THERMAL) {
+                let _ = app.emit("thermal-paused", ThermalPayload { celsius });
+            }
+        } else if paused && celsius < guard.resume_below_celsius && resume_child(&state, PAUSE_THERMAL) {
+            let _ = app.emit("thermal-resumed", ThermalPayload { celsius });
+        }
+    });
+}
@@ -1,19 +1,190 @@
 import { useState, useEffect } from "react";
-import { invoke } from "@tauri-apps/api/core";
+import { Channel, invoke } from "@tauri-apps/api/core";
 import { listen } from "@tauri-apps/api/event";
-import { open } from "@tauri-apps/plugin-dialog";
+import { open, save } from "@tauri-apps/plugin-dialog";
+import { revealItemInDir } from "@tauri-apps/plugin-opener";
 import "./App.css";
 
 // Types
-type ContentType = "track" | "playlist" | "album";
+type ContentType = "track" | "playlist" | "album" | "artist";
 type StatusType = "info" | "success" | "error" | "";
 
 interface ProgressPayload {
+  permille: number;
   percent: number;
   message: string;
   current_track: number;
   total_tracks: number;
   speed: string;
+  eta_seconds: number | null;
+  bytes_per_sec: number | null;
+  current_title: string | null;
+}
+
+// Why a failed download failed, when the backend could tell
+type ErrorKind = "invalid_url" | "auth" | "no_match" | "network" | "ffmpeg" | "disk";
+
+// Sent in per-track mode while pausing before the next song
+interface ThrottlingPayload {
+  next_track: string;
+  index: number;
+  total: number;
+  delay_ms: number;
+}
+
+interface DownloadFinishedPayload {
+  success: boolean;
+  m3u_file: string | null;
+  error_kind: ErrorKind | null;
+}
+
+interface TrackInfo {
+  title: string;
+  artist: string;
+  duration_sec: number;
+}
+
+interface UrlImport {
+  items: { line: number; url: string; content_type: ContentType }[];
+  invalid: { line: number; text: string; error: string }[];
+}
+
+interface HistoryEntry {
+  url: string;
+  content_type: string;
+  collection: string | null;
+  output_path: string;
+  timestamp: string;
+  track_count: number;
+  format: string | null;
+}
+
+interface DownloadedTrack {
+  path: string;
+  title: string;
+  artist: string;
+  album: string | null;
+  duration_sec: number | null;
+}
+
+// A downloaded song that looks broken
+interface IntegrityIssue {
+  path: string;
+  query: string;  // "Artist - Title"
+  problem: string;
+}
+
+interface DownloadLogPayload {
+  line: string;
+  severity: "error" | "warning" | "info";
+}
+
+// spotdl lyrics providers; "synced" is needed for .lrc files
+const LYRICS_PROVIDERS = ["synced", "musixmatch", "genius", "azlyrics"];
+
+// spotdl audio providers offered in the form; the chosen one is tried first
+const AUDIO_SOURCES = ["youtube-music", "youtube"];
+
+// Lines kept in the log pane
+const MAX_LOG_LINES = 200;
+
+interface AppInfo {
+  version: string;
+  spotdl_version: string | null;
+  os: string;
+  arch: string;
+}
+
+// What sync_playlist changed in the folder
+interface SyncReport {
+  added: number;
+  removed: number;
+  updated: number;
+  unchanged: number;
+  failed: number;
+  save_file: string;
+}
+
+// One step of run_self_test
+interface SelfTestStage {
+  name: string;
+  passed: boolean;
+  millis: number;
+  detail: string | null;
+}
+
+interface SelfTestReport {
+  passed: boolean;
+  stages: SelfTestStage[];
+}
+
+// A download the app didn't get to finish last time
+interface ResumeInfo {
+  url: string;
+  content_type: string;
+  output_folder: string;
+  started_at: string;
+  completed: number;
+}
+
+interface DependencyStatus {
+  spotdl_found: boolean;
+  spotdl_version: string | null;
+  ffmpeg_found: boolean;
+  ffmpeg_version: string | null;
+  ffmpeg_hint: string | null;
+}
+
+// Persisted settings; fields the form doesn't edit are passed back untouched
+interface Settings {
+  download_path: string | null;
+  threads: number;
+  conversion_threads: number | null;
+  format: string;
+  bitrate: string | null;
+  keep_download_logs: boolean;
+  notify_on_complete: boolean;
+  client_id: string | null;
+  client_secret: string | null;
+  proxy: string | null;
+  ffmpeg_path: string | null;
+  extra_path: string[];
+  output_templates: Record<string, string | null>;  // by content type, plus "default"
+  [key: string]: unknown;
+}
+
+// What download_content and retry_failed return
+interface RetagReport {
+  retagged: number;
+  missing: number;
+  failed: number;
+}
+
+interface DownloadSummary {
+  message: string;
+  downloaded: number;
+  skipped: number;
+  failed: number;
+  recovered: number;  // downloaded on a retry, also counted in downloaded
+  output_folder: string;
+  cancelled: boolean;
+  match_warnings: MatchWarning[];
+}
+
+// Sent as "download-summary" when a download ends
+interface SummaryEvent {
+  downloaded: number;
+  skipped: number;
+  failed: number;
+  recovered: number;
+  average_songs_per_min: number | null;
+  peak_songs_per_min: number | null;  // best 30s stretch
+}
+
+// A song spotdl wasn't sure it matched correctly
+interface MatchWarning {
+  track: string;
+  score: number | null;
 }
 
 interface ProgressState {
@@ -23,15 +194,95 @@ interface ProgressState {
   currentTrack: number;
   totalTracks: number;
   speed: string;
+  etaSeconds?: number | null;
+  bytesPerSec?: number | null;
+  currentTitle?: string | null;
+}
+
+// "850 KB/s", "3.2 MB/s"
+function formatRate(bytesPerSec: number): string {
+  if (bytesPerSec < 1_000_000) return `${Math.round(bytesPerSec / 1000)} KB/s`;
+  return `${(bytesPerSec / 1_000_000).toFixed(1)} MB/s`;
+}
+
+// "850 MB", "12.4 GB"
+function formatSize(bytes: number): string {
+  if (bytes < 1_000_000_000) return `${Math.round(bytes / 1_000_000)} MB`;
+  return `${(bytes / 1_000_000_000).toFixed(1)} GB`;
+}
+
+// "45s left", "3m left", "1h 5m left"
+function formatEta(seconds: number): string {
+  if (seconds < 60) return `${seconds}s left`;
+  const minutes = Math.round(seconds / 60);
+  if (minutes < 60) return `${minutes}m left`;
+  return `${Math.floor(minutes / 60)}h ${minutes % 60}m left`;
 }
 
 function App() {
   // State
-  const [contentType, setContentType] = useState<ContentType>("track");
+  const [contentType, setContentType] = useState<ContentType | null>(null);
+  const [appInfo, setAppInfo] = useState<AppInfo | null>(null);
+  const [interrupted, setInterrupted] = useState<ResumeInfo | null>(null);
+  const [errorKind, setErrorKind] = useState<ErrorKind | null>(null);
+  const [selfTest, setSelfTest] = useState<SelfTestReport | null>(null);
+  const [isSelfTesting, setIsSelfTesting] = useState(false);
+  const [debugCommand, setDebugCommand] = useState("");
+  const [debugOutput, setDebugOutput] = useState<string | null>(null);
+  const [isDebugRunning, setIsDebugRunning] = useState(false);
+  const [library, setLibrary] = useState<DownloadedTrack[] | null>(null);
+  const [integrityIssues, setIntegrityIssues] = useState<IntegrityIssue[] | null>(null);
+  const [isVerifying, setIsVerifying] = useState(false);
+  const [history, setHistory] = useState<HistoryEntry[] | null>(null);
   const [spotifyUrl, setSpotifyUrl] = useState("");
   const [threads, setThreads] = useState(4);
+  const [conversionThreads, setConversionThreads] = useState("");
+  const [format, setFormat] = useState("mp3");
+  const [bitrate, setBitrate] = useState("");
+  const [bitratePresets, setBitratePresets] = useState<Record<string, string | null>>({});
+  const [outputTemplate, setOutputTemplate] = useState("");
+  const [outputTemplates, setOutputTemplates] = useState<Record<string, string | null>>({});
+  const [rememberTemplate, setRememberTemplate] = useState(false);
+  const [audioSource, setAudioSource] = useState("");
+  const [generateM3u, setGenerateM3u] = useState(false);
+  const [perTrack, setPerTrack] = useState(false);
+  const [maxRetries, setMaxRetries] = useState(0);
+  const [trackDelaySeconds, setTrackDelaySeconds] = useState(0);
+  const [rampUp, setRampUp] = useState(false);
+  const [concurrency, setConcurrency] = useState<number | null>(null);
+  const [playlistSubfolder, setPlaylistSubfolder] = useState(false);
+  const [embedArt, setEmbedArt] = useState(true);
+  const [saveCover, setSaveCover] = useState(false);
+  const [restrictFilenames, setRestrictFilenames] = useState(false);
+  const [skipArtAndLyrics, setSkipArtAndLyrics] = useState(false);
+  const [timeoutMinutes, setTimeoutMinutes] = useState("");
+  const [overwrite, setOverwrite] = useState("skip");
+  const [extraArgs, setExtraArgs] = useState("");
+  const [freeSpace, setFreeSpace] = useState<number | null>(null);
+  const [m3uFile, setM3uFile] = useState<string | null>(null);
+  const [finishedFolder, setFinishedFolder] = useState<string | null>(null);
+  const [failedCount, setFailedCount] = useState(0);
+  const [embedLyrics, setEmbedLyrics] = useState(false);
+  const [syncedLrc, setSyncedLrc] = useState(false);
+  const [lyricsProviders, setLyricsProviders] = useState<string[]>(["synced", "musixmatch", "genius"]);
+  const [keepLogs, setKeepLogs] = useState(false);
+  const [notifyOnComplete, setNotifyOnComplete] = useState(false);
+  const [clientId, setClientId] = useState("");
+  const [clientSecret, setClientSecret] = useState("");
+  const [proxy, setProxy] = useState("");
+  const [ffmpegPath, setFfmpegPath] = useState("");
+  const [extraPath, setExtraPath] = useState("");
+  const [cookieFile, setCookieFile] = useState("");
   const [downloadPath, setDownloadPath] = useState("");
   const [isDownloading, setIsDownloading] = useState(false);
+  const [isPaused, setIsPaused] = useState(false);
+  const [previewTracks, setPreviewTracks] = useState<TrackInfo[] | null>(null);
+  const [isPreviewing, setIsPreviewing] = useState(false);
+  const [logLines, setLogLines] = useState<DownloadLogPayload[]>([]);
+  const [matchWarnings, setMatchWarnings] = useState<MatchWarning[]>([]);
+  const [missingDependencies, setMissingDependencies] = useState<string[]>([]);
+  const [isInstalling, setIsInstalling] = useState(false);
+  const [dependencyHint, setDependencyHint] = useState<string | null>(null);
   const [status, setStatus] = useState({ message: "", type: "" as StatusType });
   const [progress, setProgress] = useState<ProgressState>({
     visible: false,
@@ -45,34 +296,178 @@ function App() {
   // Initialize - get default download path and set up event listeners
   useEffect(() => {
     async function init() {
+      let savedPath: string | null = null;
+      try {
+        const settings = await invoke<Settings>("load_settings");
+        savedPath = settings.download_path;
+        setThreads(settings.threads);
+        setConversionThreads(settings.conversion_threads?.toString() ?? "");
+        setFormat(settings.format);
+        setBitrate(settings.bitrate ?? "");
+        setKeepLogs(settings.keep_download_logs);
+        setNotifyOnComplete(settings.notify_on_complete);
+        setClientId(settings.client_id ?? "");
+        setClientSecret(settings.client_secret ?? "");
+        setProxy(settings.proxy ?? "");
+        setFfmpegPath(settings.ffmpeg_path ?? "");
+        setExtraPath(settings.extra_path.join("\n"));
+        setOutputTemplates(settings.output_templates);
+      } catch (e) {
+        console.error("Failed to load settings:", e);
+      }
+
       try {
-        const path = await invoke<string>("get_download_path");
+        const path = savedPath || (await invoke<string>("get_download_path"));
         setDownloadPath(path);
       } catch (e) {
         console.error("Failed to get download path:", e);
       }
+
+      try {
+        const deps = await invoke<DependencyStatus>("check_dependencies");
+        const missing = [];
+        if (!deps.spotdl_found) missing.push("spotdl");
+        if (!deps.ffmpeg_found) missing.push("ffmpeg");
+        setMissingDependencies(missing);
+        setDependencyHint(deps.ffmpeg_hint);
+      } catch (e) {
+        console.error("Failed to check dependencies:", e);
+      }
+
+      try {
+        const presets = await invoke<{ format: string; bitrate: string | null }[]>("bitrate_presets");
+        setBitratePresets(Object.fromEntries(presets.map((preset) => [preset.format, preset.bitrate])));
+      } catch (e) {
+        console.error("Failed to get bitrate presets:", e);
+      }
+
+      try {
+        setAppInfo(await invoke<AppInfo>("get_app_version"));
+      } catch (e) {
+        console.error("Failed to get app version:", e);
+      }
+
+      try {
+        setInterrupted(await invoke<ResumeInfo | null>("resume_interrupted"));
+      } catch (e) {
+        console.error("Failed to check for an interrupted download:", e);
+      }
     }
     init();
 
     // Listen for progress updates from Rust backend
-    const unlisten = listen<ProgressPayload>("download-progress", (event) => {
-      setProgress({
-        visible: true,
-        percent: event.payload.percent,
-        text: event.payload.message,
-        currentTrack: event.payload.current_track,
-        totalTracks: event.payload.total_tracks,
-        speed: event.payload.speed,
-      });
+    // Downloads started elsewhere, such as from the queue, still report through the global event
+    const unlisten = listen<ProgressPayload>("download-progress", (event) => showProgress(event.payload));
+
+    // spotdl's stderr, for the log pane
+    const unlistenLog = listen<DownloadLogPayload>("download-log", (event) => {
+      setLogLines((lines) => [...lines, event.payload].slice(-MAX_LOG_LINES));
+    });
+
+    // pip's output while spotdl is installed, shown in the same pane
+    const unlistenSetupLog = listen<DownloadLogPayload>("setup-log", (event) => {
+      setLogLines((lines) => [...lines, event.payload].slice(-MAX_LOG_LINES));
+    });
+
+    // Songs that may have been matched to the wrong audio
+    const unlistenMatch = listen<MatchWarning>("match-warning", (event) => {
+      setMatchWarnings((warnings) => [...warnings, event.payload]);
     });
 
+    // Remember the playlist file so it can be shown after the download
+    const unlistenFinished = listen<DownloadFinishedPayload>("download-finished", (event) => {
+      setM3uFile(event.payload.m3u_file);
+      setErrorKind(event.payload.error_kind);
+    });
+
+    // Failed songs can be retried whether or not the download as a whole succeeded
+    const unlistenSummary = listen<SummaryEvent>("download-summary", (event) => {
+      setFailedCount(event.payload.failed);
+    });
+
+    // How many songs spotdl is downloading at once, which grows during a ramp-up
+    const unlistenConcurrency = listen<{ threads: number }>("concurrency", (event) => {
+      setConcurrency(event.payload.threads);
+    });
+
+    // Explain the pause between per-track songs, the next progress update replaces it
+    const unlistenThrottling = listen<ThrottlingPayload>("throttling", (event) => {
+      const { next_track, index, total, delay_ms } = event.payload;
+      setProgress((progress) => ({
+        ...progress,
+        text: `Pausing ${Math.round(delay_ms / 1000)}s to avoid rate limits before ${next_track} (${index}/${total})...`,
+      }));
+    });
+
+    // Catch up on a download that was already running before a reload. Its progress
+    // channel went with the old page, so keep asking until the download ends
+    let followTimer: number | undefined;
+    let following = false;
+    const follow = async () => {
+      try {
+        const payload = await invoke<ProgressPayload | null>("get_current_progress");
+        if (payload) {
+          following = true;
+          setIsDownloading(true);
+          showProgress(payload);
+          followTimer = window.setTimeout(follow, 1000);
+        } else if (following) {
+          setIsDownloading(false);
+        }
+      } catch (e) {
+        console.error("Failed to get current progress:", e);
+      }
+    };
+    follow();
+
     // Cleanup listener on unmount
     return () => {
       unlisten.then((fn) => fn());
+      unlistenLog.then((fn) => fn());
+      unlistenSetupLog.then((fn) => fn());
+      unlistenMatch.then((fn) => fn());
+      unlistenFinished.then((fn) => fn());
+      unlistenSummary.then((fn) => fn());
+      unlistenThrottling.then((fn) => fn());
+      unlistenConcurrency.then((fn) => fn());
+      window.clearTimeout(followTimer);
     };
   }, []);
 
+  // Free space on the drive the download would go to
+  useEffect(() => {
+    if (!downloadPath) {
+      setFreeSpace(null);
+      return;
+    }
+    invoke<number>("get_free_space", { path: downloadPath })
+      .then(setFreeSpace)
+      .catch(() => setFreeSpace(null));
+  }, [downloadPath]);
+
   // Show status message
+  const showProgress = (payload: ProgressPayload) => {
+    setProgress({
+      visible: true,
+      // Fractional, so the bar moves with every song even on long playlists
+      percent: payload.permille / 10,
+      text: payload.message,
+      currentTrack: payload.current_track,
+      totalTracks: payload.total_tracks,
+      speed: payload.speed,
+      etaSeconds: payload.eta_seconds,
+      bytesPerSec: payload.bytes_per_sec,
+      currentTitle: payload.current_title,
+    });
+  };
+
+  // Progress for a download this window started, delivered only to this window
+  const progressChannel = () => {
+    const channel = new Channel<ProgressPayload>();
+    channel.onmessage = showProgress;
+    return channel;
+  };
+
   const showStatus = (message: string, type: StatusType) => {
     setStatus({ message, type });
     if (type === "success" || type === "error") {
@@ -99,6 +494,116 @@ function App() {
     }
   };
 
+  // Cookies exported from a browser, for age-gated or members-only videos
+  const handleBrowseCookies = async () => {
+    try {
+      const selected = await open({
+        multiple: false,
+        filters: [{ name: "Cookie file", extensions: ["txt"] }],
+        title: "Select Cookie File",
+      });
+      if (selected) {
+        setCookieFile(selected as string);
+      }
+    } catch (e) {
+      console.error("Error selecting cookie file:", e);
+      showStatus(`Error: ${e}`, "error");
+    }
+  };
+
+  // Queue every link in a text file, one per line, with the current format and folder
+  const handleImportList = async () => {
+    try {
+      const selected = await open({
+        multiple: false,
+        filters: [{ name: "Link list", extensions: ["txt"] }],
+        title: "Import Spotify Links",
+      });
+      if (!selected) return;
+
+      const result = await invoke<UrlImport>("import_url_list", { filePath: selected as string });
+      const problems = result.invalid.map((item) => `line ${item.line}: ${item.error}`);
+      let queued = 0;
+      for (const item of result.items) {
+        try {
+          await invoke("enqueue_download", {
+            url: item.url,
+            options: {
+              threads,
+              conversion_threads: Number(conversionThreads) > 0 ? Number(conversionThreads) : null,
+              download_path: downloadPath,
+              format,
+              bitrate: bitrate || null,
+              overwrite,
+            },
+          });
+          queued++;
+        } catch (e) {
+          problems.push(`line ${item.line}: ${e}`);
+        }
+      }
+
+      const summary = `Queued ${queued} link(s)`;
+      showStatus(problems.length ? `${summary}, skipped ${problems.join("; ")}` : summary, problems.length ? "error" : "success");
+    } catch (e) {
+      showStatus(`${e}`, "error");
+    }
+  };
+
+  // Install spotdl with pip into ~/.venv, for users who don't have it yet
+  const handleInstallSpotdl = async () => {
+    setIsInstalling(true);
+    setLogLines([]);
+    showStatus("Installing spotdl, this can take a few minutes...", "info");
+    try {
+      const result = await invoke<{ method: string; spotdl_path: string; version: string | null }>("install_spotdl");
+      showStatus(`✓ Installed spotdl ${result.version ?? ""} at ${result.spotdl_path}`, "success");
+      setMissingDependencies((missing) => missing.filter((dep) => dep !== "spotdl"));
+    } catch (e) {
+      showStatus(`✗ ${e}`, "error");
+    } finally {
+      setIsInstalling(false);
+    }
+  };
+
+  // Pick the ffmpeg binary for installs spotdl can't find on its own
+  const handleBrowseFfmpeg = async () => {
+    try {
+      const selected = await open({
+        multiple: false,
+        title: "Select ffmpeg",
+      });
+      if (selected) {
+        setFfmpegPath(selected as string);
+      }
+    } catch (e) {
+      console.error("Error selecting ffmpeg:", e);
+      showStatus(`Error: ${e}`, "error");
+    }
+  };
+
+  // The content type comes from the pasted link
+  const handleUrlChange = async (value: string) => {
+    setSpotifyUrl(value);
+    setPreviewTracks(null);
+    try {
+      setContentType(await invoke<ContentType>("detect_content_type", { url: value }));
+    } catch {
+      // Not a complete Spotify link yet
+      setContentType(null);
+    }
+  };
+
+  // A cancelled download still says how many songs it saved
+  const showSummary = (result: DownloadSummary) => {
+    setMatchWarnings(result.match_warnings);
+    if (result.cancelled) {
+      showStatus(result.message, "info");
+    } else {
+      showStatus(`✓ ${result.message}`, "success");
+    }
+  };
+
   // Download handler
   const handleDownload = async () => {
     // Validation
@@ -107,23 +612,120 @@ function App() {
       return;
     }
 
-    if (!spotifyUrl.startsWith("https://open.spotify.com/")) {
-      showStatus("Please enter a valid Spotify URL", "error");
+    // Share links carry tracking parameters, download the canonical link instead
+    let url: string;
+    try {
+      url = await invoke<string>("normalize_url", { raw: spotifyUrl });
+      setSpotifyUrl(url);
+    } catch (e) {
+      showStatus(`${e}`, "error");
+      return;
+    }
+
+    let expectedTracks = previewTracks?.length ?? null;
+    try {
+      const kind = await invoke<ContentType>("detect_content_type", { url });
+
+      // A discography can be hundreds of songs, so show the count and ask first
+      if (kind === "artist") {
+        let tracks = previewTracks;
+        if (!tracks) {
+          setIsPreviewing(true);
+          try {
+            tracks = await invoke<TrackInfo[]>("preview_tracks", { url });
+            setPreviewTracks(tracks);
+          } finally {
+            setIsPreviewing(false);
+          }
+        }
+        if (!window.confirm(`This artist has ${tracks.length} songs. Download all of them?`)) {
+          return;
+        }
+        expectedTracks = tracks.length;
+      }
+    } catch (e) {
+      showStatus(`${e}`, "error");
+      return;
+    }
+
+    // Remember the form for next launch
+    try {
+      const settings = await invoke<Settings>("load_settings");
+      const savedTemplates =
+        rememberTemplate && contentType && outputTemplate.trim()
+          ? { ...settings.output_templates, [contentType]: outputTemplate.trim() }
+          : settings.output_templates;
+      await invoke("save_settings", {
+        settings: {
+          ...settings,
+          download_path: downloadPath,
+          threads,
+          conversion_threads: Number(conversionThreads) > 0 ? Number(conversionThreads) : null,
+          format,
+          bitrate: bitrate || null,
+          keep_download_logs: keepLogs,
+          notify_on_complete: notifyOnComplete,
+          client_id: clientId && clientSecret ? clientId : null,
+          client_secret: clientId && clientSecret ? clientSecret : null,
+          proxy: proxy.trim() || null,
+          ffmpeg_path: ffmpegPath.trim() || null,
+          extra_path: extraPath.split("\n").map((dir) => dir.trim()).filter((dir) => dir),
+          output_templates: savedTemplates,
+        },
+      });
+      setOutputTemplates(savedTemplates);
+    } catch (e) {
+      // Rejected values, such as a malformed proxy, would otherwise be silently ignored
+      showStatus(`${e}`, "error");
       return;
     }
 
     setIsDownloading(true);
+    setConcurrency(null);
+    setLogLines([]);
+    setMatchWarnings([]);
+    setM3uFile(null);
+    setFinishedFolder(null);
+    setFailedCount(0);
     setProgress({ visible: true, percent: 0, text: "Initializing...", currentTrack: 0, totalTracks: 0, speed: "" });
 
     try {
-      const result = await invoke<string>("download_content", {
-        url: spotifyUrl,
-        contentType,
-        threads,
-        downloadPath,
+      // One options object, anything left out takes the backend's default
+      const result = await invoke<DownloadSummary>("download_content", {
+        url,
+        onProgress: progressChannel(),
+        options: {
+          threads,
+          download_path: downloadPath,
+          format,
+          bitrate: bitrate || null,
+          output_template: outputTemplate || null,
+          generate_m3u: generateM3u && !perTrack && !rampUp,
+          per_track: perTrack,
+          max_retries: perTrack ? maxRetries : 0,
+          delay_between_tracks_ms: perTrack && trackDelaySeconds > 0 ? trackDelaySeconds * 1000 : null,
+          ramp_up: rampUp && !perTrack,
+          create_playlist_subfolder: playlistSubfolder && contentType !== "track",
+          embed_album_art: embedArt,
+          save_cover_file: embedArt && saveCover && !skipArtAndLyrics,
+          restrict_filenames: restrictFilenames,
+          skip_art_and_lyrics: skipArtAndLyrics,
+          conversion_threads: Number(conversionThreads) > 0 ? Number(conversionThreads) : null,
+          timeout_secs: Number(timeoutMinutes) > 0 ? Math.round(Number(timeoutMinutes) * 60) : null,
+          overwrite,
+          extra_spotdl_args: extraArgs.split(/\s+/).filter((arg) => arg),
+          expected_tracks: expectedTracks,
+          cookie_file: cookieFile || null,
+          embed_plain_lyrics: embedLyrics && !skipArtAndLyrics,
+          generate_synced_lrc: syncedLrc && !skipArtAndLyrics,
+          lyrics_providers: (embedLyrics || syncedLrc) && !skipArtAndLyrics ? lyricsProviders : [],
+          audio_providers: audioSource ? [audioSource, ...AUDIO_SOURCES.filter((p) => p !== audioSource)] : [],
+        },
       });
 
-      showStatus(`✓ ${result}`, "success");
+      showSummary(result);
+      setFinishedFolder(result.output_folder);
+      if (result.cancelled) return;
       setSpotifyUrl("");
       
       setTimeout(() => {
@@ -134,6 +736,279 @@ function App() {
       setProgress({ visible: false, percent: 0, text: "", currentTrack: 0, totalTracks: 0, speed: "" });
     } finally {
       setIsDownloading(false);
+      setIsPaused(false);
+    }
+  };
+
+  // Make the download folder match the playlist, deleting songs that left it
+  const handleSync = async () => {
+    if (!confirm(`Songs removed from this ${contentType} will also be deleted from ${downloadPath}. Continue?`)) return;
+    setIsDownloading(true);
+    setProgress({ visible: true, percent: 0, text: "Syncing...", currentTrack: 0, totalTracks: 0, speed: "" });
+
+    try {
+      const report = await invoke<SyncReport>("sync_playlist", { url: spotifyUrl, path: downloadPath });
+      showStatus(
+        `✓ Synced: ${report.added} added, ${report.removed} removed, ${report.updated} updated, ${report.unchanged} unchanged` +
+          (report.failed > 0 ? `, ${report.failed} failed` : ""),
+        "success"
+      );
+      setFinishedFolder(downloadPath);
+    } catch (e) {
+      showStatus(`✗ ${e}`, "error");
+    } finally {
+      setProgress({ visible: false, percent: 0, text: "", currentTrack: 0, totalTracks: 0, speed: "" });
+      setIsDownloading(false);
+      setIsPaused(false);
+    }
+  };
+
+  // Save one track under a file name the user picks
+  const handleSaveTrackAs = async () => {
+    const file = await save({
+      defaultPath: downloadPath,
+      title: "Save Track As",
+      filters: [{ name: "Audio", extensions: [format] }],
+    });
+    if (!file) return;
+    setIsDownloading(true);
+    setProgress({ visible: true, percent: 0, text: "Downloading track...", currentTrack: 0, totalTracks: 1, speed: "" });
+
+    try {
+      const saved = await invoke<string>("download_single", { url: spotifyUrl, outputFile: file });
+      showStatus(`✓ Saved ${saved}`, "success");
+    } catch (e) {
+      showStatus(`✗ ${e}`, "error");
+    } finally {
+      setProgress({ visible: false, percent: 0, text: "", currentTrack: 0, totalTracks: 0, speed: "" });
+      setIsDownloading(false);
+      setIsPaused(false);
+    }
+  };
+
+  // Refresh the tags of songs already in the download folder, without downloading them again
+  const handleRetag = async () => {
+    setIsDownloading(true);
+    setProgress({ visible: true, percent: 0, text: "Re-tagging...", currentTrack: 0, totalTracks: 0, speed: "" });
+
+    try {
+      const report = await invoke<RetagReport>("retag", { url: spotifyUrl, path: downloadPath });
+      showStatus(
+        `✓ Re-tagged ${report.retagged} song(s)` +
+          (report.missing > 0 ? `, ${report.missing} not in the folder` : "") +
+          (report.failed > 0 ? `, ${report.failed} failed` : ""),
+        "success"
+      );
+    } catch (e) {
+      showStatus(`✗ ${e}`, "error");
+    } finally {
+      setProgress({ visible: false, percent: 0, text: "", currentTrack: 0, totalTracks: 0, speed: "" });
+      setIsDownloading(false);
+      setIsPaused(false);
+    }
+  };
+
+  // Download just the songs that failed last time, with the same options
+  const handleRetry = async () => {
+    setIsDownloading(true);
+    setFailedCount(0);
+    setProgress({ visible: true, percent: 0, text: "Retrying failed tracks...", currentTrack: 0, totalTracks: 0, speed: "" });
+
+    try {
+      const result = await invoke<DownloadSummary>("retry_failed", { onProgress: progressChannel() });
+      showSummary(result);
+    } catch (e) {
+      showStatus(`✗ ${e}`, "error");
+    } finally {
+      setProgress({ visible: false, percent: 0, text: "", currentTrack: 0, totalTracks: 0, speed: "" });
+      setIsDownloading(false);
+      setIsPaused(false);
+    }
+  };
+
+  // Pick up the download that was cut short when the app last closed
+  const handleContinueInterrupted = async () => {
+    setInterrupted(null);
+    setIsDownloading(true);
+    setProgress({ visible: true, percent: 0, text: "Continuing interrupted download...", currentTrack: 0, totalTracks: 0, speed: "" });
+
+    try {
+      const result = await invoke<DownloadSummary>("continue_interrupted", { onProgress: progressChannel() });
+      showSummary(result);
+      setFinishedFolder(result.output_folder);
+    } catch (e) {
+      showStatus(`✗ ${e}`, "error");
+    } finally {
+      setProgress({ visible: false, percent: 0, text: "", currentTrack: 0, totalTracks: 0, speed: "" });
+      setIsDownloading(false);
+      setIsPaused(false);
+    }
+  };
+
+  // Download a known track into a temp folder to see if the setup works
+  const handleSelfTest = async () => {
+    setIsSelfTesting(true);
+    setSelfTest(null);
+    try {
+      setSelfTest(await invoke<SelfTestReport>("run_self_test"));
+    } catch (e) {
+      showStatus(`${e}`, "error");
+    } finally {
+      setIsSelfTesting(false);
+    }
+  };
+
+  // Run an allowlisted spotdl command, e.g. "url <link>", and show its raw output
+  const handleDebugRun = async () => {
+    setIsDebugRunning(true);
+    try {
+      setDebugOutput(await invoke<string>("run_spotdl_debug", { args: debugCommand.trim().split(/\s+/) }));
+    } catch (e) {
+      setDebugOutput(`${e}`);
+    } finally {
+      setIsDebugRunning(false);
+    }
+  };
+
+  const handleDiscardInterrupted = async () => {
+    try {
+      await invoke("discard_interrupted");
+      setInterrupted(null);
+    } catch (e) {
+      showStatus(`${e}`, "error");
+    }
+  };
+
+  // List the songs behind the link without downloading them
+  const handlePreview = async () => {
+    setIsPreviewing(true);
+    try {
+      setPreviewTracks(await invoke<TrackInfo[]>("preview_tracks", { url: spotifyUrl }));
+    } catch (e) {
+      showStatus(`${e}`, "error");
+    } finally {
+      setIsPreviewing(false);
+    }
+  };
+
+  // Read the download folder's songs when the library is opened
+  const handleLibraryToggle = async (open: boolean) => {
+    if (!open) return;
+    try {
+      setLibrary(await invoke<DownloadedTrack[]>("list_downloads", { path: downloadPath }));
+    } catch (e) {
+      setLibrary(null);
+      showStatus(`${e}`, "error");
+    }
+  };
+
+  // Delete a bad download, so downloading its playlist again fetches it anew
+  const handleDeleteTrack = async (track: DownloadedTrack) => {
+    if (!confirm(`Delete ${track.path}?`)) return;
+    try {
+      await invoke("delete_download", { path: track.path });
+      setLibrary((library) => library?.filter((entry) => entry.path !== track.path) ?? null);
+    } catch (e) {
+      showStatus(`${e}`, "error");
+    }
+  };
+
+  // Look for empty, cut off or corrupt songs in the download folder
+  const handleVerifyLibrary = async () => {
+    setIsVerifying(true);
+    try {
+      const issues = await invoke<IntegrityIssue[]>("verify_downloads", { path: downloadPath });
+      setIntegrityIssues(issues);
+      if (issues.length === 0) showStatus("✓ All songs look intact", "success");
+    } catch (e) {
+      showStatus(`${e}`, "error");
+    } finally {
+      setIsVerifying(false);
+    }
+  };
+
+  // Delete a broken song, so downloading its playlist again fetches it anew
+  const handleDeleteBroken = async (issue: IntegrityIssue) => {
+    try {
+      await invoke("delete_download", { path: issue.path });
+      setIntegrityIssues((issues) => issues?.filter((entry) => entry.path !== issue.path) ?? null);
+      setLibrary((library) => library?.filter((entry) => entry.path !== issue.path) ?? null);
+    } catch (e) {
+      showStatus(`${e}`, "error");
+    }
+  };
+
+  // Move the download folder's songs into another folder, leaving any whose name is taken there
+  const handleMoveLibrary = async () => {
+    try {
+      const selected = await open({ directory: true, multiple: false, title: "Move Songs To" });
+      if (!selected) return;
+      const moved = await invoke<number>("move_downloads", { from: downloadPath, to: selected as string, pattern: null });
+      showStatus(`✓ Moved ${moved} song(s) to ${selected}`, "success");
+      setLibrary(await invoke<DownloadedTrack[]>("list_downloads", { path: downloadPath }));
+    } catch (e) {
+      showStatus(`${e}`, "error");
+    }
+  };
+
+  // Past downloads, newest first
+  const handleHistoryToggle = async (open: boolean) => {
+    if (!open) return;
+    try {
+      setHistory((await invoke<HistoryEntry[]>("get_history")).reverse());
+    } catch (e) {
+      showStatus(`${e}`, "error");
+    }
+  };
+
+  const handleClearHistory = async () => {
+    try {
+      await invoke("clear_history");
+      setHistory([]);
+    } catch (e) {
+      showStatus(`${e}`, "error");
+    }
+  };
+
+  // Fill the form from a past download, ready to start again
+  const handleRedownload = (entry: HistoryEntry) => {
+    handleUrlChange(entry.url);
+    setDownloadPath(entry.output_path);
+    if (entry.format) setFormat(entry.format);
+    window.scrollTo({ top: 0, behavior: "smooth" });
+  };
+
+  const toggleLyricsProvider = (provider: string, enabled: boolean) => {
+    setLyricsProviders((current) =>
+      enabled ? LYRICS_PROVIDERS.filter((p) => p === provider || current.includes(p)) : current.filter((p) => p !== provider)
+    );
+  };
+
+  const handleTestCredentials = async () => {
+    try {
+      await invoke("test_spotify_credentials", { clientId, clientSecret });
+      showStatus("Spotify credentials work", "success");
+    } catch (e) {
+      showStatus(`${e}`, "error");
+    }
+  };
+
+  // Pause/resume handler
+  const handlePauseToggle = async () => {
+    try {
+      await invoke(isPaused ? "resume_download" : "pause_download");
+      setIsPaused(!isPaused);
+    } catch (e) {
+      showStatus(`${e}`, "error");
+    }
+  };
+
+  // Give up on the current song and go on with the next
+  const handleSkip = async () => {
+    try {
+      await invoke("skip_current_track");
+    } catch (e) {
+      showStatus(`${e}`, "error");
     }
   };
 
@@ -141,6 +1016,7 @@ function App() {
   const handleCancel = async () => {
     try {
       await invoke("cancel_download");
+      setIsPaused(false);
       showStatus("Download cancelled", "info");
       setIsDownloading(false);
       setProgress({ visible: false, percent: 0, text: "", currentTrack: 0, totalTracks: 0, speed: "" });
@@ -149,6 +1025,19 @@ function App() {
     }
   };
 
+  // Cancel the running download and everything queued after it
+  const handleCancelAll = async () => {
+    try {
+      const result = await invoke<{ removed: number; active_cancelled: boolean }>("cancel_all");
+      setIsPaused(false);
+      showStatus(`Download queue cancelled, ${result.removed} queued download(s) removed`, "info");
+      setIsDownloading(false);
+      setProgress({ visible: false, percent: 0, text: "", currentTrack: 0, totalTracks: 0, speed: "" });
+    } catch (e) {
+      showStatus(`Failed to cancel: ${e}`, "error");
+    }
+  };
+
   return (
     <div className="container">
       {/* Header */}
@@ -164,21 +1053,110 @@ function App() {
 
       {/* Main Content */}
       <main className="main-content">
-        {/* Content Type Selection */}
+        {/* Content Type, detected from the link */}
+        {contentType && (
+          <div className="form-group">
+            <label>Content Type</label>
+            <p className="detected-type">{contentType.charAt(0).toUpperCase() + contentType.slice(1)} (detected from the link)</p>
+          </div>
+        )}
+
+        {/* Audio Format Selection */}
+        <div className="form-group">
+          <label htmlFor="format">Audio Format</label>
+          <select
+            id="format"
+            className="select-input"
+            value={format}
+            onChange={(e) => setFormat(e.target.value)}
+          >
+            <option value="mp3">MP3</option>
+            <option value="flac">FLAC</option>
+            <option value="opus">Opus</option>
+            <option value="m4a">M4A</option>
+            <option value="ogg">OGG</option>
+            <option value="wav">WAV</option>
+          </select>
+        </div>
+
+        {/* Bitrate Selection */}
+        <div className="form-group">
+          <label htmlFor="bitrate">Bitrate</label>
+          <select
+            id="bitrate"
+            className="select-input"
+            value={bitrate}
+            onChange={(e) => setBitrate(e.target.value)}
+          >
+            <option value="">
+              {bitratePresets[format] ? `Default (${bitratePresets[format]})` : "Default (no re-encoding limit)"}
+            </option>
+            <option value="auto">Auto (match source)</option>
+            <option value="128k">128k</option>
+            <option value="192k">192k</option>
+            <option value="256k">256k</option>
+            <option value="320k">320k</option>
+            <option value="disable">Disable (lossless formats)</option>
+          </select>
+        </div>
+
+        {/* Audio Source Selection */}
         <div className="form-group">
-          <label htmlFor="contentType">Content Type</label>
+          <label htmlFor="audioSource">Audio Source</label>
           <select
-            id="contentType"
+            id="audioSource"
             className="select-input"
-            value={contentType}
-            onChange={(e) => setContentType(e.target.value as ContentType)}
+            value={audioSource}
+            onChange={(e) => setAudioSource(e.target.value)}
           >
-            <option value="track">Track</option>
-            <option value="playlist">Playlist</option>
-            <option value="album">Album</option>
+            <option value="">spotdl default</option>
+            <option value="youtube-music">YouTube Music first</option>
+            <option value="youtube">YouTube first</option>
           </select>
         </div>
 
+        {/* Overwrite Policy */}
+        <div className="form-group">
+          <label htmlFor="overwrite">Existing Files</label>
+          <select
+            id="overwrite"
+            className="select-input"
+            value={overwrite}
+            onChange={(e) => setOverwrite(e.target.value)}
+          >
+            <option value="skip">Skip</option>
+            <option value="metadata">Update metadata only</option>
+            <option value="force">Download again and replace</option>
+          </select>
+        </div>
+
+        {/* Lyrics */}
+        <div className="form-group">
+          <label>Lyrics</label>
+          <label className="checkbox-label">
+            <input type="checkbox" checked={embedLyrics} onChange={(e) => setEmbedLyrics(e.target.checked)} />
+            Embed lyrics in the audio files
+          </label>
+          <label className="checkbox-label">
+            <input type="checkbox" checked={syncedLrc} onChange={(e) => setSyncedLrc(e.target.checked)} />
+            Save synced lyrics as .lrc files
+          </label>
+          {(embedLyrics || syncedLrc) && (
+            <div className="provider-list">
+              {LYRICS_PROVIDERS.map((provider) => (
+                <label key={provider} className="checkbox-label">
+                  <input
+                    type="checkbox"
+                    checked={lyricsProviders.includes(provider)}
+                    onChange={(e) => toggleLyricsProvider(provider, e.target.checked)}
+                  />
+                  {provider}
+                </label>
+              ))}
+            </div>
+          )}
+        </div>
+
         {/* URL Input */}
         <div className="form-group">
           <label htmlFor="spotifyUrl">Spotify URL</label>
@@ -188,26 +1166,139 @@ function App() {
             className="text-input"
             placeholder="https://open.spotify.com/..."
             value={spotifyUrl}
-            onChange={(e) => setSpotifyUrl(e.target.value)}
+            onChange={(e) => handleUrlChange(e.target.value)}
           />
+          <button className="btn-secondary btn-preview" onClick={handleImportList}>
+            Import links from file
+          </button>
+        </div>
+
+        {/* Threads Selection */}
+        <div className="form-group">
+          <label htmlFor="threads">Parallel Downloads</label>
+          <div className="slider-container">
+            <input
+              type="range"
+              id="threads"
+              className="slider"
+              min={1}
+              max={8}
+              value={threads}
+              onChange={(e) => setThreads(Number(e.target.value))}
+            />
+            <span className="slider-value">{threads}</span>
+          </div>
         </div>
 
-        {/* Threads Selection (only for playlists/albums) */}
+        {/* Re-encoding is CPU heavy, so it can run with fewer threads */}
+        {format !== "m4a" && format !== "opus" && (
+          <div className="form-group">
+            <label htmlFor="conversionThreads">Max parallel conversions (optional)</label>
+            <input
+              type="number"
+              id="conversionThreads"
+              className="text-input"
+              min={1}
+              max={threads}
+              placeholder={`Same as parallel downloads (${threads})`}
+              value={conversionThreads}
+              onChange={(e) => setConversionThreads(e.target.value)}
+            />
+            <div className="field-hint">Keeps the computer usable during big FLAC or WAV downloads</div>
+          </div>
+        )}
+
+        {/* M3U playlist (only for playlists/albums) */}
         {(contentType === "playlist" || contentType === "album") && (
           <div className="form-group">
-            <label htmlFor="threads">Parallel Downloads</label>
-            <div className="slider-container">
-              <input
-                type="range"
-                id="threads"
-                className="slider"
-                min={1}
-                max={8}
-                value={threads}
-                onChange={(e) => setThreads(Number(e.target.value))}
-              />
-              <span className="slider-value">{threads}</span>
-            </div>
+            <label className="checkbox-label">
+              <input type="checkbox" checked={generateM3u} onChange={(e) => setGenerateM3u(e.target.checked)} />
+              Create an .m3u8 playlist file
+            </label>
+          </div>
+        )}
+
+        {/* Album art, embedded and as a folder image for media servers */}
+        <div className="form-group">
+          <label className="checkbox-label">
+            <input type="checkbox" checked={embedArt} onChange={(e) => setEmbedArt(e.target.checked)} />
+            Embed album art
+          </label>
+          <label className="checkbox-label">
+            <input
+              type="checkbox"
+              checked={embedArt && saveCover}
+              disabled={!embedArt}
+              onChange={(e) => setSaveCover(e.target.checked)}
+            />
+            Also save a cover.jpg in the folder (for Plex/Jellyfin)
+          </label>
+          {format === "wav" && embedArt && <div className="field-hint">WAV files can't carry album art</div>}
+        </div>
+
+        {/* Plain ASCII file names for FAT/NTFS drives and older players */}
+        <div className="form-group">
+          <label className="checkbox-label">
+            <input type="checkbox" checked={restrictFilenames} onChange={(e) => setRestrictFilenames(e.target.checked)} />
+            Use ASCII-only file names (for SD cards and MP3 players)
+          </label>
+        </div>
+
+        {/* Leave art and lyrics to the user's own tagging tool */}
+        <div className="form-group">
+          <label className="checkbox-label">
+            <input type="checkbox" checked={skipArtAndLyrics} onChange={(e) => setSkipArtAndLyrics(e.target.checked)} />
+            Skip album art and lyrics (faster, for tagging in another tool)
+          </label>
+        </div>
+
+        {/* Keep a playlist's songs together instead of loose in the download folder */}
+        {contentType && contentType !== "track" && (
+          <div className="form-group">
+            <label className="checkbox-label">
+              <input type="checkbox" checked={playlistSubfolder} onChange={(e) => setPlaylistSubfolder(e.target.checked)} />
+              Save into a folder named after the {contentType}
+            </label>
+          </div>
+        )}
+
+        {/* One spotdl run per song, so a stuck song can be skipped */}
+        {contentType && contentType !== "track" && (
+          <div className="form-group">
+            <label className="checkbox-label">
+              <input type="checkbox" checked={perTrack} onChange={(e) => setPerTrack(e.target.checked)} />
+              Download one track at a time (allows skipping a stuck track, no playlist file)
+            </label>
+            {perTrack && (
+              <>
+                <label htmlFor="maxRetries">Retries after a network error or rate limit</label>
+                <input
+                  id="maxRetries"
+                  type="number"
+                  min={0}
+                  max={5}
+                  value={maxRetries}
+                  onChange={(e) => setMaxRetries(Math.min(5, Math.max(0, Number(e.target.value) || 0)))}
+                  disabled={isDownloading}
+                />
+                <label htmlFor="trackDelay">Pause between tracks (seconds, helps with rate limits)</label>
+                <input
+                  id="trackDelay"
+                  type="number"
+                  min={0}
+                  max={60}
+                  value={trackDelaySeconds}
+                  onChange={(e) => setTrackDelaySeconds(Math.min(60, Math.max(0, Number(e.target.value) || 0)))}
+                  disabled={isDownloading}
+                />
+              </>
+            )}
+            {!perTrack && (
+              <label className="checkbox-label">
+                <input type="checkbox" checked={rampUp} onChange={(e) => setRampUp(e.target.checked)} />
+                Start with one parallel download and ramp up (avoids early rate limiting, no playlist file)
+              </label>
+            )}
           </div>
         )}
 
@@ -227,13 +1318,214 @@ function App() {
               Browse
             </button>
           </div>
+          {freeSpace != null && <div className="field-hint">{formatSize(freeSpace)} free</div>}
+        </div>
+
+        {/* File naming, passed to spotdl's --output */}
+        <div className="form-group">
+          <label htmlFor="outputTemplate">Filename Template (optional)</label>
+          <input
+            type="text"
+            id="outputTemplate"
+            className="text-input"
+            placeholder={
+              (contentType && outputTemplates[contentType]) || outputTemplates.default || "{artist}/{album}/{title}.{output-ext}"
+            }
+            value={outputTemplate}
+            onChange={(e) => setOutputTemplate(e.target.value)}
+          />
+          {contentType && outputTemplate.trim() && (
+            <label className="checkbox-label">
+              <input type="checkbox" checked={rememberTemplate} onChange={(e) => setRememberTemplate(e.target.checked)} />
+              Use this template for every {contentType} download
+            </label>
+          )}
+        </div>
+
+        {/* Give up on downloads that hang */}
+        <div className="form-group">
+          <label htmlFor="timeoutMinutes">Timeout in Minutes (optional)</label>
+          <input
+            type="number"
+            id="timeoutMinutes"
+            className="text-input"
+            min="1"
+            placeholder="No timeout"
+            value={timeoutMinutes}
+            onChange={(e) => setTimeoutMinutes(e.target.value)}
+          />
+        </div>
+
+        {/* Extra flags passed straight to spotdl */}
+        <div className="form-group">
+          <label htmlFor="extraArgs">Extra spotdl Arguments (optional)</label>
+          <input
+            type="text"
+            id="extraArgs"
+            className="text-input"
+            placeholder="--sponsor-block"
+            value={extraArgs}
+            onChange={(e) => setExtraArgs(e.target.value)}
+          />
+        </div>
+
+        {/* Cookies for age-gated or members-only matches */}
+        <div className="form-group">
+          <label htmlFor="cookieFile">Cookie File (optional)</label>
+          <div className="path-selector">
+            <input
+              type="text"
+              id="cookieFile"
+              className="text-input path-input"
+              placeholder="cookies.txt exported from your browser"
+              value={cookieFile}
+              onChange={(e) => setCookieFile(e.target.value)}
+            />
+            <button className="btn-secondary" onClick={handleBrowseCookies}>
+              Browse
+            </button>
+          </div>
+        </div>
+
+        {/* Proxy for restricted networks */}
+        <div className="form-group">
+          <label htmlFor="proxy">Proxy (optional)</label>
+          <input
+            type="text"
+            id="proxy"
+            className="text-input"
+            placeholder="http://proxy.example.com:8080"
+            value={proxy}
+            onChange={(e) => setProxy(e.target.value)}
+          />
+        </div>
+
+        {/* For ffmpeg and python installs the app's PATH doesn't include */}
+        <div className="form-group">
+          <label htmlFor="ffmpegPath">ffmpeg Location (optional)</label>
+          <div className="path-selector">
+            <input
+              type="text"
+              id="ffmpegPath"
+              className="text-input path-input"
+              placeholder="Found automatically"
+              value={ffmpegPath}
+              onChange={(e) => setFfmpegPath(e.target.value)}
+            />
+            <button className="btn-secondary" onClick={handleBrowseFfmpeg}>
+              Browse
+            </button>
+          </div>
+        </div>
+
+        <div className="form-group">
+          <label htmlFor="extraPath">Extra PATH Folders (optional, one per line)</label>
+          <textarea
+            id="extraPath"
+            className="text-input"
+            rows={2}
+            placeholder="/opt/homebrew/bin"
+            value={extraPath}
+            onChange={(e) => setExtraPath(e.target.value)}
+          />
         </div>
 
+        {/* Own Spotify app credentials, to avoid shared rate limits */}
+        <div className="form-group">
+          <label htmlFor="clientId">Spotify API Credentials (optional)</label>
+          <input
+            type="text"
+            id="clientId"
+            className="text-input"
+            placeholder="Client ID"
+            value={clientId}
+            onChange={(e) => setClientId(e.target.value)}
+          />
+          <div className="path-selector credentials-row">
+            <input
+              type="password"
+              className="text-input path-input"
+              placeholder="Client secret"
+              value={clientSecret}
+              onChange={(e) => setClientSecret(e.target.value)}
+            />
+            <button className="btn-secondary" onClick={handleTestCredentials} disabled={!clientId || !clientSecret}>
+              Test
+            </button>
+          </div>
+        </div>
+
+        {/* Log files and notifications */}
+        <div className="form-group">
+          <label className="checkbox-label">
+            <input type="checkbox" checked={keepLogs} onChange={(e) => setKeepLogs(e.target.checked)} />
+            Save a log file for each download
+          </label>
+          <label className="checkbox-label">
+            <input
+              type="checkbox"
+              checked={notifyOnComplete}
+              onChange={(e) => setNotifyOnComplete(e.target.checked)}
+            />
+            Notify me when a download finishes
+          </label>
+        </div>
+
+        {/* Setup warning when required tools are missing */}
+        {missingDependencies.length > 0 && (
+          <div className="status-message show error">
+            Missing {missingDependencies.join(" and ")}. Install {missingDependencies.length > 1 ? "them" : "it"} and restart the app to download.
+            {dependencyHint && <div>{dependencyHint}</div>}
+            {missingDependencies.includes("spotdl") && (
+              <button className="btn-secondary btn-preview" onClick={handleInstallSpotdl} disabled={isInstalling}>
+                {isInstalling ? "Installing spotdl..." : "Install spotdl for me"}
+              </button>
+            )}
+          </div>
+        )}
+
+        {/* A download the app didn't get to finish */}
+        {interrupted && !isDownloading && (
+          <div className="status-message show info">
+            A {interrupted.content_type} download into {interrupted.output_folder} didn't finish
+            {interrupted.completed > 0 && ` (${interrupted.completed} song${interrupted.completed > 1 ? "s" : ""} done)`}.
+            <div>
+              <button className="btn-secondary btn-preview" onClick={handleContinueInterrupted}>
+                Continue
+              </button>
+              <button className="btn-secondary btn-preview" onClick={handleDiscardInterrupted}>
+                Discard
+              </button>
+            </div>
+          </div>
+        )}
+
+        {/* Track preview */}
+        <button
+          className="btn-secondary btn-preview"
+          onClick={handlePreview}
+          disabled={isPreviewing || isDownloading || !contentType || missingDependencies.length > 0}
+        >
+          {isPreviewing ? "Loading tracks..." : "Preview tracks"}
+        </button>
+        {previewTracks && (
+          <ol className="preview-list">
+            {previewTracks.map((track, i) => (
+              <li key={i}>
+                {track.artist} - {track.title}
+                <span className="preview-duration">
+                  {Math.floor(track.duration_sec / 60)}:{String(track.duration_sec % 60).padStart(2, "0")}
+                </span>
+              </li>
+            ))}
+          </ol>
+        )}
+
         {/* Download Button */}
         <button
           className="btn-primary"
           onClick={handleDownload}
-          disabled={isDownloading}
+          disabled={isDownloading || missingDependencies.length > 0}
         >
           <svg className="btn-icon" viewBox="0 0 24 24" fill="none" xmlns="http://www.w3.org/2000/svg">
             <path d="M12 16L7 11L8.4 9.55L11 12.15V4H13V12.15L15.6 9.55L17 11L12 16ZM6 20C5.45 20 4.979 19.804 4.587 19.412C4.195 19.02 3.999 18.549 4 18V15H6V18H18V15H20V18C20 18.55 19.804 19.021 19.412 19.413C19.02 19.805 18.549 20.001 18 20H6Z" fill="currentColor"/>
@@ -241,6 +1533,35 @@ function App() {
           {isDownloading ? "Downloading..." : "Download"}
         </button>
 
+        {/* Keep an offline mirror of a changing playlist */}
+        {(contentType === "playlist" || contentType === "album") && (
+          <button
+            className="btn-secondary btn-preview"
+            onClick={handleSync}
+            disabled={isDownloading || missingDependencies.length > 0}
+          >
+            Sync folder with {contentType}
+          </button>
+        )}
+        {contentType === "track" && (
+          <button
+            className="btn-secondary btn-preview"
+            onClick={handleSaveTrackAs}
+            disabled={isDownloading || missingDependencies.length > 0}
+          >
+            Save track as...
+          </button>
+        )}
+        {contentType && (
+          <button
+            className="btn-secondary btn-preview"
+            onClick={handleRetag}
+            disabled={isDownloading || missingDependencies.length > 0}
+          >
+            Update tags of downloaded songs
+          </button>
+        )}
+
         {/* Status Message */}
         {status.message && (
           <div className={`status-message show ${status.type}`}>
@@ -248,15 +1569,61 @@ function App() {
           </div>
         )}
 
+        {/* Ways out of the last download's error */}
+        {errorKind === "network" && !isDownloading && spotifyUrl && (
+          <button className="btn-secondary btn-preview" onClick={handleDownload}>
+            Try again
+          </button>
+        )}
+        {errorKind === "auth" && !isDownloading && (
+          <button className="btn-secondary btn-preview" onClick={() => document.getElementById("clientId")?.focus()}>
+            Enter Spotify API credentials
+          </button>
+        )}
+
+        {/* Songs that failed in the last download */}
+        {failedCount > 0 && !isDownloading && (
+          <button className="btn-secondary btn-preview" onClick={handleRetry}>
+            Retry {failedCount} failed track{failedCount > 1 ? "s" : ""}
+          </button>
+        )}
+
+        {/* Output folder of the last download */}
+        {finishedFolder && !isDownloading && (
+          <button
+            className="btn-secondary btn-preview"
+            onClick={() => invoke("open_download_folder", { path: finishedFolder }).catch((e) => showStatus(`${e}`, "error"))}
+          >
+            Show in folder
+          </button>
+        )}
+
+        {/* Playlist file from the last download */}
+        {m3uFile && !isDownloading && (
+          <button className="btn-secondary btn-preview" onClick={() => revealItemInDir(m3uFile)}>
+            Show playlist file
+          </button>
+        )}
+
         {/* Progress Bar */}
         {progress.visible && (
           <div className="progress-container">
             <div className="progress-header">
-              <span className="progress-text">{progress.text}</span>
+              <span className="progress-text">
+                {progress.text}
+                {progress.currentTitle && <span className="progress-title"> {progress.currentTitle}</span>}
+              </span>
               {progress.totalTracks > 0 && (
                 <span className="progress-stats">
                   {progress.currentTrack}/{progress.totalTracks}
                   {progress.speed && <span className="progress-speed"> • {progress.speed}</span>}
+                  {concurrency != null && <span className="progress-speed"> • {concurrency} at once</span>}
+                  {progress.bytesPerSec != null && (
+                    <span className="progress-speed"> • {formatRate(progress.bytesPerSec)}</span>
+                  )}
+                  {progress.etaSeconds != null && progress.etaSeconds > 0 && (
+                    <span className="progress-speed"> • {formatEta(progress.etaSeconds)}</span>
+                  )}
                 </span>
               )}
             </div>
@@ -267,20 +1634,173 @@ function App() {
               />
             </div>
             <div className="progress-footer">
-              <div className="progress-percent">{progress.percent}%</div>
+              <div className="progress-percent">{Math.floor(progress.percent)}%</div>
               {isDownloading && progress.percent < 100 && (
-                <button className="btn-cancel" onClick={handleCancel}>
-                  Cancel
-                </button>
+                <div className="progress-actions">
+                  <button className="btn-pause" onClick={handlePauseToggle}>
+                    {isPaused ? "Resume" : "Pause"}
+                  </button>
+                  {perTrack && (
+                    <button className="btn-pause" onClick={handleSkip}>
+                      Skip track
+                    </button>
+                  )}
+                  <button className="btn-cancel" onClick={handleCancel}>
+                    Cancel
+                  </button>
+                  <button className="btn-cancel" onClick={handleCancelAll}>
+                    Cancel all
+                  </button>
+                </div>
               )}
             </div>
           </div>
         )}
+
+        {/* Songs already in the download folder */}
+        <details className="log-pane" onToggle={(e) => handleLibraryToggle(e.currentTarget.open)}>
+          <summary>Library{library && ` (${library.length} songs)`}</summary>
+          {library && (
+            <ol className="preview-list">
+              {library.map((track) => (
+                <li key={track.path} title={track.path}>
+                  {track.artist ? `${track.artist} - ${track.title}` : track.title}
+                  {track.album && <span className="field-hint"> ({track.album})</span>}
+                  {track.duration_sec != null && (
+                    <span className="preview-duration">
+                      {Math.floor(track.duration_sec / 60)}:{String(track.duration_sec % 60).padStart(2, "0")}
+                    </span>
+                  )}
+                  <button className="btn-secondary" onClick={() => handleDeleteTrack(track)} disabled={isDownloading}>
+                    Delete
+                  </button>
+                </li>
+              ))}
+            </ol>
+          )}
+          {library && library.length > 0 && (
+            <>
+              <button className="btn-secondary btn-preview" onClick={handleMoveLibrary}>
+                Move songs to...
+              </button>
+              <button className="btn-secondary btn-preview" onClick={handleVerifyLibrary} disabled={isVerifying}>
+                {isVerifying ? "Checking..." : "Check files"}
+              </button>
+            </>
+          )}
+          {integrityIssues && integrityIssues.length > 0 && (
+            <>
+              <p className="field-hint">
+                These songs look broken. Delete them and download their playlist again to fetch them anew.
+              </p>
+              <ol className="preview-list">
+                {integrityIssues.map((issue) => (
+                  <li key={issue.path} title={issue.path}>
+                    {issue.query}
+                    <span className="field-hint"> ({issue.problem})</span>
+                    <button className="btn-secondary" onClick={() => handleDeleteBroken(issue)} disabled={isDownloading}>
+                      Delete
+                    </button>
+                  </li>
+                ))}
+              </ol>
+            </>
+          )}
+        </details>
+
+        {/* Past downloads */}
+        <details className="log-pane" onToggle={(e) => handleHistoryToggle(e.currentTarget.open)}>
+          <summary>History{history && ` (${history.length})`}</summary>
+          {history && history.length > 0 && (
+            <>
+              <ol className="preview-list">
+                {history.map((entry) => (
+                  <li key={entry.timestamp + entry.url}>
+                    {new Date(entry.timestamp).toLocaleString()}: {entry.content_type}, {entry.track_count} song(s)
+                    {entry.format && ` as ${entry.format.toUpperCase()}`}
+                    <button
+                      className="btn-secondary btn-preview"
+                      onClick={() => handleRedownload(entry)}
+                      disabled={isDownloading}
+                      title={entry.url}
+                    >
+                      Download again
+                    </button>
+                  </li>
+                ))}
+              </ol>
+              <button className="btn-secondary btn-preview" onClick={handleClearHistory}>
+                Clear history
+              </button>
+            </>
+          )}
+        </details>
+
+        {/* Songs to listen to before trusting them */}
+        {matchWarnings.length > 0 && (
+          <details className="log-pane" open>
+            <summary>Check these songs ({matchWarnings.length} uncertain matches)</summary>
+            <ul>
+              {matchWarnings.map((warning) => (
+                <li key={warning.track}>
+                  {warning.track}
+                  {warning.score !== null && ` (match score ${Math.round(warning.score)})`}
+                </li>
+              ))}
+            </ul>
+          </details>
+        )}
+
+        {/* spotdl log, collapsed by default */}
+        {logLines.length > 0 && (
+          <details className="log-pane">
+            <summary>Log ({logLines.length} lines)</summary>
+            <pre>
+              {logLines.map((entry, i) => (
+                <div key={i} className={`log-line ${entry.severity}`}>{entry.line}</div>
+              ))}
+            </pre>
+          </details>
+        )}
       </main>
 
       {/* Footer */}
       <footer className="footer">
         <p>Powered by spotdl</p>
+        {appInfo && (
+          <details className="about">
+            <summary>About</summary>
+            {/* Selectable, so it can be pasted into a bug report */}
+            <pre>
+              Spotify Downloader {appInfo.version}
+              {"\n"}spotdl {appInfo.spotdl_version ?? "not found"}
+              {"\n"}{appInfo.os} ({appInfo.arch})
+            </pre>
+            <button className="btn-secondary" onClick={handleSelfTest} disabled={isSelfTesting || isDownloading}>
+              {isSelfTesting ? "Testing..." : "Run self-test"}
+            </button>
+            {selfTest && (
+              <pre>
+                {selfTest.stages
+                  .map((stage) => `${stage.passed ? "✓" : "✗"} ${stage.name} (${stage.millis} ms) ${stage.detail ?? ""}`)
+                  .join("\n")}
+              </pre>
+            )}
+            <div className="path-selector">
+              <input
+                type="text"
+                className="url-input"
+                placeholder="spotdl url https://open.spotify.com/track/..."
+                value={debugCommand}
+                onChange={(e) => setDebugCommand(e.target.value.replace(/^\s*spotdl\s+/, ""))}
+              />
+              <button className="btn-secondary" onClick={handleDebugRun} disabled={isDebugRunning || !debugCommand.trim()}>
+                {isDebugRunning ? "Running..." : "Run"}
+              </button>
+            </div>
+            {debugOutput && <pre>{debugOutput}</pre>}
+          </details>
+        )}
       </footer>
     </div>
   );